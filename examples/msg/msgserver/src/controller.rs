@@ -1,11 +1,14 @@
 use crate::interface_client::*;
 use crate::user_manager::{IUserManager, USERMANAGER};
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result};
 use log::*;
 use netxserver::prelude::*;
 use packer::{LogOn, LogOnRes, User};
 use std::sync::Arc;
 
+/// The group of all logged in users.
+const LOBBY: &str = "lobby";
+
 //实现服务器接口和业务,用来给服务器调用
 //Realize the server interface and business, used to call the server
 #[build(ServerController)]
@@ -47,10 +50,10 @@ impl Drop for ServerController {
 impl IServerController for ServerController {
     #[inline]
     async fn connect(&self) -> Result<()> {
-        if let Some(peer) = self.token.get_peer().await {
+        if let Some(addr) = self.token.get_remote_addr().await {
             info!(
                 "addr:{} session {} connect",
-                peer.addr(),
+                addr,
                 self.token.get_session_id()
             )
         }
@@ -58,7 +61,7 @@ impl IServerController for ServerController {
     }
     #[inline]
     async fn disconnect(&self) -> Result<()> {
-        let user = USERMANAGER.find(self.token.get_session_id()).await;
+        let user = self.token.get_extension::<User>().await;
         if let Some(peer) = self.token.get_peer().await {
             if let Some(user) = user {
                 info!(
@@ -89,12 +92,20 @@ impl IServerController for ServerController {
         info!("{} is logon", msg.nickname);
 
         if USERMANAGER.check_nickname(&msg.nickname).await {
-            USERMANAGER
-                .add(User {
-                    nickname: msg.nickname,
-                    session_id: self.token.get_session_id(),
-                })
-                .await;
+            let user = User {
+                nickname: msg.nickname,
+                session_id: self.token.get_session_id(),
+            };
+            // 当前会话的用户保存在token上
+            // the user of this session is kept on the token
+            self.token.insert_extension(user.clone()).await;
+            // 用昵称查找会话
+            // find the session by nickname
+            self.token.bind_key(&user.nickname).await?;
+            // 加入大厅接收聊天消息
+            // join the lobby to receive chat messages
+            self.token.join_group(LOBBY).await?;
+            USERMANAGER.add(user).await;
 
             Ok(LogOnRes {
                 success: true,
@@ -114,37 +125,32 @@ impl IServerController for ServerController {
     }
     #[inline]
     async fn talk(&self, msg: String) -> Result<()> {
-        let current = USERMANAGER.find(self.token.get_session_id()).await;
-        if let Some(current_user) = current {
-            for user in USERMANAGER.get_users().await {
-                if user.session_id != current_user.session_id {
-                    let token = self.token.get_token(user.session_id).await?;
-                    if let Some(token) = token {
-                        let peer = impl_ref!(token=>IClient);
-                        peer.message(current_user.nickname.clone(), msg.clone(), false)
-                            .await;
-                    }
-                }
-            }
-            Ok(())
-        } else {
-            bail!("not login")
+        let current_user = self
+            .token
+            .get_extension::<User>()
+            .await
+            .context("not login")?;
+        // 消息只序列化一次,发给大厅里的其他用户
+        // the message is serialized once and sent to the other users in the lobby
+        let failed =
+            call_peer!(@broadcast self.token=>LOBBY, 2001; current_user.nickname, msg, false);
+        for (session_id, err) in failed {
+            warn!("talk to session {} error:{}", session_id, err);
         }
+        Ok(())
     }
     #[inline]
     async fn to(&self, target_nickname: String, msg: String) -> Result<()> {
-        let current_user = USERMANAGER
-            .find(self.token.get_session_id())
+        let current_user = self
+            .token
+            .get_extension::<User>()
             .await
             .context("not login")?;
-        let target_user = USERMANAGER
-            .find_by_nickname(target_nickname.clone())
-            .await
-            .with_context(|| format!("not found {}", target_nickname))?;
         let token = self
             .token
-            .get_token(target_user.session_id)
+            .get_tokens_by_key(&target_nickname)
             .await?
+            .pop()
             .with_context(|| format!("not found {}", target_nickname))?;
 
         let peer = impl_ref!(token=>IClient);
@@ -154,18 +160,16 @@ impl IServerController for ServerController {
     }
     #[inline]
     async fn ping(&self, target_nickname: String, time: i64) -> Result<i64> {
-        let current_user = USERMANAGER
-            .find(self.token.get_session_id())
+        let current_user = self
+            .token
+            .get_extension::<User>()
             .await
             .context("not login")?;
-        let target_user = USERMANAGER
-            .find_by_nickname(target_nickname.clone())
-            .await
-            .with_context(|| format!("not found {}", target_nickname))?;
         let token = self
             .token
-            .get_token(target_user.session_id)
+            .get_tokens_by_key(&target_nickname)
             .await?
+            .pop()
             .with_context(|| format!("not found {}", target_nickname))?;
         let peer = impl_ref!(token=>IClient);
         Ok(peer.ping(current_user.nickname.clone(), time).await?)
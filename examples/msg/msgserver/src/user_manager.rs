@@ -36,24 +36,6 @@ impl UserManager {
         self.users.push(user)
     }
 
-    pub fn find(&self, session_id: i64) -> Option<User> {
-        for user in self.users.iter() {
-            if user.session_id == session_id {
-                return Some(user.clone());
-            }
-        }
-        None
-    }
-
-    pub fn find_by_nickname(&self, nickname: String) -> Option<User> {
-        for user in self.users.iter() {
-            if user.nickname == nickname {
-                return Some(user.clone());
-            }
-        }
-        None
-    }
-
     pub fn remove(&mut self, session_id: i64) -> Option<User> {
         for (index, user) in self.users.iter().enumerate() {
             if user.session_id == session_id {
@@ -70,8 +52,6 @@ impl UserManager {
 
 pub(crate) trait IUserManager {
     async fn add(&self, user: User);
-    async fn find(&self, session_id: i64) -> Option<User>;
-    async fn find_by_nickname(&self, nickname: String) -> Option<User>;
     async fn remove(&self, session_id: i64) -> Option<User>;
     async fn get_users(&self) -> Vec<User>;
     async fn check_nickname(&self, nickname: &str) -> bool;
@@ -85,17 +65,6 @@ impl IUserManager for Actor<UserManager> {
         })
         .await
     }
-    #[inline]
-    async fn find(&self, session_id: i64) -> Option<User> {
-        self.inner_call(|inner| async move { inner.get_mut().find(session_id) })
-            .await
-    }
-    #[inline]
-    async fn find_by_nickname(&self, nickname: String) -> Option<User> {
-        self.inner_call(|inner| async move { inner.get_mut().find_by_nickname(nickname) })
-            .await
-    }
-
     #[inline]
     async fn remove(&self, session_id: i64) -> Option<User> {
         self.inner_call(|inner| async move { inner.get_mut().remove(session_id) })
@@ -13,60 +13,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .filter_module("mio", LevelFilter::Error)
         .init();
 
-    #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
-    let client = {
-        use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
-        let ssl_connector = {
-            let mut connector = SslConnector::builder(SslMethod::tls())?;
-            connector.set_verify(SslVerifyMode::PEER);
-            connector.set_ca_file("./ca_test/CA.crt")?;
-            connector.set_private_key_file("./ca_test/client-key.pem", SslFiletype::PEM)?;
-            connector.set_certificate_chain_file("./ca_test/client-crt.pem")?;
-            connector.check_private_key()?;
-            connector.build()
-        };
-
-        NetXClient::new_ssl(
-            ServerOption::new("127.0.0.1:6666".into(), "".into(), "123123".into(), 5000),
-            DefaultSessionStore::default(),
-            "localhost".to_string(),
-            ssl_connector,
-        )
-    };
-
+    // the test server certificate has no subject alternative name, which rustls rejects
+    let builder = TlsConfigBuilder::new("localhost")
+        .ca("./ca_test/CA.crt")
+        .identity("./ca_test/client-crt.pem", "./ca_test/client-key.pem");
+
+    // use openssl when enabled, otherwise rustls
+    #[cfg(feature = "use_openssl")]
+    let tls_config = builder.build_openssl()?;
     #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
-    let client = {
-        use anyhow::Context;
-        use rustls_pemfile::{certs, private_key};
-        use std::convert::TryFrom;
-        use std::fs::File;
-        use std::io::BufReader;
-        use std::sync::Arc;
-        use tokio_rustls::rustls::pki_types::ServerName;
-        use tokio_rustls::rustls::ClientConfig;
-
-        let cert_file = &mut BufReader::new(File::open("./ca_test/client-crt.pem")?);
-        let key_file = &mut BufReader::new(File::open("./ca_test/client-key.pem")?);
-
-        let keys = private_key(key_file)?.context("bad private key")?;
-
-        let cert_chain = certs(cert_file).map(|r| r.unwrap()).collect();
-
-        let tls_config = ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(Arc::new(RustlsAcceptAnyCertVerifier))
-            .with_client_auth_cert(cert_chain, keys)
-            .expect("bad certificate/key");
-
-        let connector = tokio_rustls::TlsConnector::from(Arc::new(tls_config));
+    let tls_config = builder.danger_accept_invalid_certs(true).build_rustls()?;
 
-        NetXClient::new_tls(
-            ServerOption::new("127.0.0.1:6666".into(), "".into(), "123123".into(), 5000),
-            DefaultSessionStore::default(),
-            ServerName::try_from("localhost")?,
-            connector,
-        )
-    };
+    let client = NetXClient::new_with_tls(
+        ServerOption::new("127.0.0.1:6666".into(), "".into(), "123123".into(), 5000),
+        DefaultSessionStore::default(),
+        tls_config,
+    );
 
     let server = impl_ref!(client=>IServer);
     log::info!("{}", server.hello("123").await?);
@@ -17,7 +17,7 @@ pub trait ITestController {
 }
 
 pub struct TestController {
-    _token: NetxToken<Self>,
+    token: NetxToken<Self>,
 }
 
 unsafe impl Send for TestController {}
@@ -26,6 +26,9 @@ unsafe impl Sync for TestController {}
 #[build_impl]
 impl ITestController for TestController {
     async fn hello(&self, msg: String) -> Result<String> {
+        if let Some(identity) = self.token.get_identity().await {
+            log::info!("client identity:{}", identity);
+        }
         log::info!("client:{}", msg);
         Ok(format!("{} hello", msg))
     }
@@ -56,6 +59,6 @@ impl ICreateController for ImplCreateController {
         &self,
         token: NetxToken<Self::Controller>,
     ) -> Result<Arc<Self::Controller>> {
-        Ok(Arc::new(TestController { _token: token }))
+        Ok(Arc::new(TestController { token }))
     }
 }
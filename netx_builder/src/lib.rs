@@ -5,6 +5,7 @@ extern crate proc_macro;
 
 use global_info::*;
 use proc_macro::TokenStream;
+use proc_macro2::Span;
 use proc_macro_roids::namespace_parameter;
 use quote::{format_ident, quote};
 use syn::{
@@ -106,6 +107,9 @@ fn get_function_tt(tag_id: i32, func_name: String, rt: Type) -> u8 {
 
 /// Generates client implementation functions based on the provided function information.
 fn get_impl_func_client(funcs: &[FuncInfo]) -> Vec<proc_macro2::TokenStream> {
+    // mixed site names can not collide with the parameters of the interface functions
+    let selected = format_ident!("__netx_selected", span = Span::mixed_site());
+    let client = format_ident!("__netx_client", span = Span::mixed_site());
     let mut ret = Vec::new();
     for func in funcs {
         let fn_name = format_ident!("{}", func.func_name);
@@ -117,14 +121,18 @@ fn get_impl_func_client(funcs: &[FuncInfo]) -> Vec<proc_macro2::TokenStream> {
             0 => {
                 ret.push(quote! {
                     async fn #fn_name(#inputs) {
-                       call!(@run_not_err self.client=>#tag;#(#input_names ,)*);
+                       let #selected=self.client.select_client();
+                       let #client=T::get_client(&#selected);
+                       call!(@run_not_err #client=>#tag;#(#input_names ,)*);
                     }
                 });
             }
             1 => {
                 ret.push(quote! {
                     async fn #fn_name(#inputs) #output{
-                        call!(@checkrun self.client=>#tag;#(#input_names ,)*);
+                        let #selected=self.client.select_client();
+                        let #client=T::get_client(&#selected);
+                        call!(@checkrun #client=>#tag;#(#input_names ,)*);
                         Ok(())
                     }
                 });
@@ -132,7 +140,9 @@ fn get_impl_func_client(funcs: &[FuncInfo]) -> Vec<proc_macro2::TokenStream> {
             2 => {
                 ret.push(quote! {
                     async fn #fn_name(#inputs) #output{
-                       Ok(call!(self.client=>#tag;#(#input_names ,)*))
+                       let #selected=self.client.select_client();
+                       let #client=T::get_client(&#selected);
+                       Ok(call!(#client=>#tag;#(#input_names ,)*))
                     }
                 });
             }
@@ -208,8 +218,8 @@ pub fn build_client(args: TokenStream, input: TokenStream) -> TokenStream {
 
         }
 
-        impl<T:SessionSave+'static> #impl_interface_struct_name<std::sync::Arc<Actor<NetXClient<T>>>>{
-            pub fn new_impl(client:std::sync::Arc<Actor<NetXClient<T>>>)->impl #interface_name{
+        impl<T:ISelectClient> #impl_interface_struct_name<T>{
+            pub fn new_impl(client:T)->impl #interface_name{
                 #impl_interface_struct_name{
                     client
                 }
@@ -225,12 +235,7 @@ pub fn build_client(args: TokenStream, input: TokenStream) -> TokenStream {
         }
 
         #[async_trait::async_trait]
-        impl<T:SessionSave+'static> #interface_name for #impl_interface_struct_name<std::sync::Arc<Actor<NetXClient<T>>>>{
-            #(#impl_func)*
-        }
-
-        #[async_trait::async_trait]
-        impl<'a,T:SessionSave+'static> #interface_name for #impl_interface_struct_name<&'a std::sync::Arc<Actor<NetXClient<T>>>>{
+        impl<T:ISelectClient> #interface_name for #impl_interface_struct_name<T>{
             #(#impl_func)*
         }
     };
@@ -0,0 +1,323 @@
+use super::{INetXClient, INextClientInner, NetxClientArc, RetResult, SessionSave};
+use data_rw::{Data, DataOwnedReader};
+use oneshot::{channel as oneshot, Receiver, Sender};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+/// A call of a batch, sent with the other calls in one batch buffer.
+struct BatchEntry {
+    serial: i64,
+    data: Data,
+    /// The sender of the result, `None` for a command without a result.
+    tx: Option<Sender<crate::error::Result<DataOwnedReader>>>,
+}
+
+/// Packs several calls into one buffer.
+///
+/// Calls are added with `call!(@batch ...)`, `call!(@batch_checkrun ...)` and `call!(@batch_run ...)`,
+/// then sent together by `send`. The server replies with the results in one buffer,
+/// each call still gets its own result through its `BatchResult`.
+pub struct NetXBatch<T> {
+    client: NetxClientArc<T>,
+    entries: Vec<BatchEntry>,
+}
+
+impl<T: SessionSave + 'static> NetXBatch<T> {
+    /// Creates an empty batch.
+    ///
+    /// # Parameters
+    ///
+    /// * `client` - The client the batch is sent through.
+    ///
+    /// # Returns
+    ///
+    /// * `NetXBatch<T>` - The batch.
+    #[inline]
+    pub fn new(client: &NetxClientArc<T>) -> NetXBatch<T> {
+        NetXBatch {
+            client: client.clone(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Generates a new serial number for a call of the batch.
+    #[inline]
+    pub fn new_serial(&self) -> i64 {
+        self.client.new_serial()
+    }
+
+    /// Gets the number of calls.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether the batch has no calls.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a call waiting for a result.
+    ///
+    /// # Parameters
+    ///
+    /// * `serial` - The serial number of the call.
+    /// * `data` - The call, without the call tag.
+    ///
+    /// # Returns
+    ///
+    /// * `BatchResult<R>` - The result of the call, available once the batch is sent.
+    #[doc(hidden)]
+    #[inline]
+    pub fn add_call<R>(&mut self, serial: i64, data: Data) -> BatchResult<R> {
+        let (tx, rx) = oneshot();
+        self.entries.push(BatchEntry {
+            serial,
+            data,
+            tx: Some(tx),
+        });
+        BatchResult {
+            serial,
+            rx,
+            _result: PhantomData,
+        }
+    }
+
+    /// Adds a command without a result.
+    ///
+    /// # Parameters
+    ///
+    /// * `data` - The command, without the call tag.
+    #[doc(hidden)]
+    #[inline]
+    pub fn add_run(&mut self, data: Data) {
+        self.entries.push(BatchEntry {
+            serial: 0,
+            data,
+            tx: None,
+        });
+    }
+
+    /// Sends the calls of the batch in one buffer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An error if the batch could not be sent, every `BatchResult` then fails.
+    pub async fn send(self) -> crate::error::Result<()> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        if !self.client.is_connect() {
+            self.client.connect_network().await?;
+        }
+
+        let data = Self::get_batch_buff(&self.entries);
+        let mut serials = Vec::with_capacity(self.entries.len());
+        for entry in self.entries {
+            if let Some(tx) = entry.tx {
+                if let Err(err) = self.client.insert_result(entry.serial, tx).await {
+                    Self::close_results(&self.client, serials).await;
+                    return Err(err);
+                }
+                serials.push(entry.serial);
+            }
+        }
+
+        if let Err(err) = self.client.run(data).await {
+            Self::close_results(&self.client, serials).await;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Constructs the batch buffer: the number of calls followed by each call.
+    #[inline]
+    fn get_batch_buff(entries: &[BatchEntry]) -> Data {
+        let mut data = Data::with_capacity(128 * entries.len());
+        data.write_fixed(2600u32);
+        data.write_fixed(entries.len() as u32);
+        for entry in entries {
+            data.write_fixed(&entry.data[..]);
+        }
+        data
+    }
+
+    /// Fails the registered calls of a batch that was not sent.
+    async fn close_results(client: &NetxClientArc<T>, serials: Vec<i64>) {
+        for serial in serials {
+            client
+                .set_error(serial, crate::error::Error::SerialClose(serial))
+                .await;
+        }
+    }
+}
+
+/// The result of a call added to a `NetXBatch`.
+pub struct BatchResult<R> {
+    serial: i64,
+    rx: Receiver<crate::error::Result<DataOwnedReader>>,
+    _result: PhantomData<fn() -> R>,
+}
+
+impl<R> BatchResult<R> {
+    /// Gets the serial number of the call.
+    #[inline]
+    pub fn get_serial(&self) -> i64 {
+        self.serial
+    }
+
+    /// Waits for the result of the call.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<RetResult>` - The result, or an error if the call failed or the batch was not sent.
+    pub async fn result(self) -> crate::error::Result<RetResult> {
+        match self.rx.await {
+            Err(_) => Err(crate::error::Error::SerialClose(self.serial)),
+            Ok(data) => RetResult::from(data?),
+        }
+    }
+
+    /// Waits for the call and checks that it succeeded.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An error if the call returned an error.
+    #[inline]
+    pub async fn check(self) -> crate::error::Result<()> {
+        self.result().await?.check()?;
+        Ok(())
+    }
+}
+
+impl<R: DeserializeOwned + 'static> BatchResult<R> {
+    /// Waits for the call and deserializes its result.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<R>` - The result, or an error if the call returned an error.
+    #[inline]
+    pub async fn get(self) -> crate::error::Result<R> {
+        let mut ret = self.result().await?.check()?;
+        ret.deserialize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{DefaultSessionStore, NetXClient, ServerOption};
+
+    fn new_batch() -> NetXBatch<DefaultSessionStore> {
+        // nothing listens on port 1, connects are refused
+        let client = NetXClient::new(
+            ServerOption::new("127.0.0.1:1".into(), "test".into(), "123".into(), 1000),
+            DefaultSessionStore::default(),
+        );
+        NetXBatch::new(&client)
+    }
+
+    /// Encodes the body of a result like an entry of the 2700 batch result, after the serial.
+    fn result_entry(result: Option<i32>, error: Option<(i32, &str)>) -> DataOwnedReader {
+        let mut data = Data::new();
+        match error {
+            Some((error_id, msg)) => {
+                data.write_fixed(true);
+                data.write_fixed(error_id);
+                data.write_fixed(msg);
+            }
+            None => {
+                data.write_fixed(false);
+                data.write_fixed(result.is_some() as u32);
+                if let Some(result) = result {
+                    data.write_fixed(Data::pack_from(result).unwrap().into_inner());
+                }
+            }
+        }
+        DataOwnedReader::new(data.into_inner())
+    }
+
+    #[tokio::test]
+    async fn batch_buff_holds_every_call() -> anyhow::Result<()> {
+        let mut batch = new_batch();
+        let call: BatchResult<i32> = call!(@batch batch=>100; 41i32);
+        let check = call!(@batch_checkrun batch=>101; "check");
+        call!(@batch_run batch=>102; 1u8, 2u8);
+        assert_eq!(batch.len(), 3);
+
+        let mut dr = DataOwnedReader::new(
+            NetXBatch::<DefaultSessionStore>::get_batch_buff(&batch.entries).into_inner(),
+        );
+        assert_eq!(dr.read_fixed::<u32>()?, 2600);
+        assert_eq!(dr.read_fixed::<u32>()?, 3);
+        let expected = [
+            (2u8, 100, call.get_serial(), 1),
+            (1, 101, check.get_serial(), 1),
+            (0, 102, 0, 2),
+        ];
+        for (tt, cmd, serial, args_count) in expected {
+            let mut entry = DataOwnedReader::new(dr.read_fixed_buf()?.to_vec());
+            assert_eq!(entry.read_fixed::<u8>()?, tt);
+            assert_eq!(entry.read_fixed::<i32>()?, cmd);
+            let entry_serial = entry.read_fixed::<i64>()?;
+            if serial != 0 {
+                assert_eq!(entry_serial, serial);
+            }
+            assert_eq!(entry.read_fixed::<i32>()?, args_count);
+        }
+        assert_eq!(dr.get_offset(), dr.len());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn batch_results_resolve_from_their_entry() -> anyhow::Result<()> {
+        let mut batch = new_batch();
+        let call: BatchResult<i32> = call!(@batch batch=>100; 41i32);
+        let check = call!(@batch_checkrun batch=>101; "check");
+        let failed: BatchResult<i32> = call!(@batch batch=>102;);
+        assert_ne!(call.get_serial(), check.get_serial());
+
+        let mut senders = batch
+            .entries
+            .iter_mut()
+            .map(|entry| entry.tx.take().unwrap());
+        senders
+            .next()
+            .unwrap()
+            .send(Ok(result_entry(Some(42), None)))
+            .unwrap();
+        senders
+            .next()
+            .unwrap()
+            .send(Ok(result_entry(None, None)))
+            .unwrap();
+        senders
+            .next()
+            .unwrap()
+            .send(Ok(result_entry(None, Some((7, "bad")))))
+            .unwrap();
+
+        assert_eq!(call.get().await?, 42);
+        check.check().await?;
+        match failed.get().await {
+            Err(crate::error::Error::CallError(7, msg)) => assert_eq!(msg, "bad"),
+            other => panic!("unexpected result:{:?}", other.map(|_| ())),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unsent_batch_fails_every_result() -> anyhow::Result<()> {
+        let mut batch = new_batch();
+        let call: BatchResult<i32> = call!(@batch batch=>100; 41i32);
+        let serial = call.get_serial();
+        call!(@batch_run batch=>101;);
+        assert!(batch.send().await.is_err());
+        assert!(matches!(
+            call.result().await,
+            Err(crate::error::Error::SerialClose(closed)) if closed == serial
+        ));
+        Ok(())
+    }
+}
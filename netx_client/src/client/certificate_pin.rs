@@ -0,0 +1,174 @@
+#[cfg(feature = "use_openssl")]
+use openssl::ssl::{SslConnectorBuilder, SslVerifyMode};
+
+/// A SHA-256 fingerprint the server certificate must match.
+///
+/// Pinning accepts a server only by its fingerprint, so self-signed servers
+/// can be trusted without disabling verification. The chain, host name and
+/// validity period are not checked, the TLS signatures still are.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CertificatePin {
+    /// SHA-256 of the DER encoded certificate.
+    Certificate([u8; 32]),
+    /// SHA-256 of the DER encoded SubjectPublicKeyInfo, which survives re-issuing with the same key.
+    PublicKey([u8; 32]),
+}
+
+impl CertificatePin {
+    /// Creates a certificate pin from a hex encoded SHA-256 fingerprint.
+    ///
+    /// # Parameters
+    ///
+    /// * `fingerprint` - The fingerprint as hex, colons are allowed (`openssl x509 -fingerprint -sha256`).
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<CertificatePin>` - The pin, or an error if the fingerprint is not 32 bytes of hex.
+    #[inline]
+    pub fn certificate(fingerprint: &str) -> anyhow::Result<CertificatePin> {
+        Ok(CertificatePin::Certificate(parse_fingerprint(fingerprint)?))
+    }
+
+    /// Creates a public key pin from a hex encoded SHA-256 fingerprint.
+    ///
+    /// # Parameters
+    ///
+    /// * `fingerprint` - The fingerprint of the SubjectPublicKeyInfo as hex, colons are allowed.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<CertificatePin>` - The pin, or an error if the fingerprint is not 32 bytes of hex.
+    #[inline]
+    pub fn public_key(fingerprint: &str) -> anyhow::Result<CertificatePin> {
+        Ok(CertificatePin::PublicKey(parse_fingerprint(fingerprint)?))
+    }
+
+    /// Checks the pin against the fingerprints of a certificate.
+    ///
+    /// # Parameters
+    ///
+    /// * `cert_sha256` - SHA-256 of the DER encoded certificate.
+    /// * `public_key_sha256` - SHA-256 of the DER encoded SubjectPublicKeyInfo.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether the pin matches.
+    #[inline]
+    pub(crate) fn matches(&self, cert_sha256: &[u8], public_key_sha256: &[u8]) -> bool {
+        match self {
+            CertificatePin::Certificate(pin) => pin[..] == *cert_sha256,
+            CertificatePin::PublicKey(pin) => pin[..] == *public_key_sha256,
+        }
+    }
+}
+
+/// Parses a hex encoded SHA-256 fingerprint.
+#[inline]
+fn parse_fingerprint(fingerprint: &str) -> anyhow::Result<[u8; 32]> {
+    let hex = fingerprint.replace(':', "");
+    // checked byte by byte, so slicing stays on char boundaries and signs like `+` are rejected
+    anyhow::ensure!(
+        hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()),
+        "fingerprint:{} is not a sha256 hex string",
+        fingerprint
+    );
+    let mut bytes = [0u8; 32];
+    for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = hex_value(pair[0]) << 4 | hex_value(pair[1]);
+    }
+    Ok(bytes)
+}
+
+/// Gets the value of an ASCII hex digit.
+#[inline]
+fn hex_value(digit: u8) -> u8 {
+    match digit {
+        b'0'..=b'9' => digit - b'0',
+        b'a'..=b'f' => digit - b'a' + 10,
+        _ => digit - b'A' + 10,
+    }
+}
+
+/// Makes an OpenSSL connector accept servers only by the pinned fingerprints.
+///
+/// This is the OpenSSL equivalent of `RustlsPinnedCertVerifier`.
+///
+/// # Parameters
+///
+/// * `connector` - The connector builder to configure.
+/// * `pins` - The accepted fingerprints, the server leaf certificate must match one of them.
+#[cfg(feature = "use_openssl")]
+pub fn set_openssl_cert_pins(connector: &mut SslConnectorBuilder, pins: Vec<CertificatePin>) {
+    connector.set_verify_callback(SslVerifyMode::PEER, move |_, ctx| {
+        // chain errors are ignored, the leaf is checked against the pins only
+        if ctx.error_depth() != 0 {
+            return true;
+        }
+        let cert = match ctx.current_cert() {
+            Some(cert) => cert,
+            None => return false,
+        };
+        let cert_sha256 = match cert.digest(openssl::hash::MessageDigest::sha256()) {
+            Ok(digest) => digest,
+            Err(_) => return false,
+        };
+        let public_key_sha256 = match cert.public_key().and_then(|key| key.public_key_to_der()) {
+            Ok(der) => openssl::sha::sha256(&der),
+            Err(_) => return false,
+        };
+        let pinned = pins
+            .iter()
+            .any(|pin| pin.matches(&cert_sha256, &public_key_sha256));
+        if !pinned {
+            log::error!("server certificate does not match any pin");
+        }
+        pinned
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str = "00112233445566778899aabbccddeeffFFEEDDCCBBAA99887766554433221100";
+
+    #[test]
+    fn parses_hex_with_and_without_colons() -> anyhow::Result<()> {
+        let bytes = parse_fingerprint(FINGERPRINT)?;
+        assert_eq!(bytes[..4], [0x00, 0x11, 0x22, 0x33]);
+        assert_eq!(bytes[16..20], [0xFF, 0xEE, 0xDD, 0xCC]);
+
+        let with_colons = FINGERPRINT
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| std::str::from_utf8(pair).unwrap())
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(parse_fingerprint(&with_colons)?, bytes);
+        assert_eq!(
+            CertificatePin::public_key(FINGERPRINT)?,
+            CertificatePin::PublicKey(bytes)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_non_hex_input() {
+        let plus = format!("+f{}", &FINGERPRINT[2..]);
+        // 64 bytes, but 32 characters of two bytes each
+        let non_ascii = "é".repeat(32);
+        assert_eq!(non_ascii.len(), 64);
+        let invalid = [
+            plus.as_str(),
+            non_ascii.as_str(),
+            &FINGERPRINT[..62],
+            "",
+            &FINGERPRINT.replace('a', "g"),
+        ];
+        for fingerprint in invalid {
+            assert!(parse_fingerprint(fingerprint).is_err(), "{}", fingerprint);
+        }
+        let mixed = format!("é{}", &FINGERPRINT[2..]);
+        assert!(parse_fingerprint(&mixed).is_err());
+    }
+}
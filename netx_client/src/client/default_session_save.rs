@@ -5,6 +5,8 @@ use super::SessionSave;
 pub struct DefaultSessionStore {
     /// The session ID.
     session_id: i64,
+    /// The session resume token.
+    resume_token: Vec<u8>,
 }
 
 impl SessionSave for DefaultSessionStore {
@@ -16,4 +18,45 @@ impl SessionSave for DefaultSessionStore {
     fn store_session_id(&mut self, session_id: i64) {
         self.session_id = session_id
     }
+    /// Retrieves the session resume token.
+    fn get_resume_token(&self) -> Vec<u8> {
+        self.resume_token.clone()
+    }
+    /// Stores the session resume token.
+    fn store_resume_token(&mut self, resume_token: Vec<u8>) {
+        self.resume_token = resume_token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A store written before resume tokens, with only the session ID methods.
+    #[derive(Default)]
+    struct SessionIdStore {
+        session_id: i64,
+    }
+
+    impl SessionSave for SessionIdStore {
+        fn get_session_id(&self) -> i64 {
+            self.session_id
+        }
+        fn store_session_id(&mut self, session_id: i64) {
+            self.session_id = session_id
+        }
+    }
+
+    #[test]
+    fn resume_token_defaults_to_empty() {
+        let mut store = SessionIdStore::default();
+        store.store_session_id(7);
+        store.store_resume_token(vec![1, 2, 3]);
+        assert_eq!(store.get_session_id(), 7);
+        assert!(store.get_resume_token().is_empty());
+
+        let mut store = DefaultSessionStore::default();
+        store.store_resume_token(vec![1, 2, 3]);
+        assert_eq!(store.get_resume_token(), vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,281 @@
+use super::ServerOption;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How the server address to connect to is chosen.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum BalanceStrategy {
+    /// Always try the addresses in order, `addr` first, failing over to the next one.
+    #[default]
+    Priority,
+    /// Start each connect at the address after the one the last connect started at.
+    RoundRobin,
+    /// Start each connect at a random address.
+    Random,
+}
+
+/// The health of a server address.
+#[derive(Clone, Debug)]
+pub struct EndpointStats {
+    /// The address of the server.
+    pub addr: String,
+    /// Whether the address is tried before the failed ones.
+    pub healthy: bool,
+    /// The number of failed connects since the last successful one.
+    pub failures: u32,
+    /// Whether the client is using this address.
+    pub current: bool,
+}
+
+/// A server address with its health and its own session.
+struct Endpoint {
+    /// The address of the server.
+    addr: String,
+    /// The number of failed connects since the last successful one.
+    failures: u32,
+    /// The address is skipped until this time, unless all addresses failed.
+    down_until: Option<Instant>,
+    /// The session ID issued by this server.
+    session_id: i64,
+    /// The resume token issued by this server.
+    resume_token: Vec<u8>,
+}
+
+impl Endpoint {
+    /// Checks whether the address is not skipped.
+    #[inline]
+    fn is_healthy(&self, now: Instant) -> bool {
+        match self.down_until {
+            Some(down_until) => down_until <= now,
+            None => true,
+        }
+    }
+}
+
+/// The server addresses of a client, `addr` first followed by `endpoints`.
+pub(crate) struct Endpoints {
+    list: Vec<Endpoint>,
+    strategy: BalanceStrategy,
+    retry: Duration,
+    /// The index the next round robin connect starts at.
+    next: usize,
+    /// The index of the address in use, its session is the one in the `SessionSave`.
+    current: usize,
+}
+
+impl Endpoints {
+    /// Creates the addresses of a server option.
+    ///
+    /// # Parameters
+    ///
+    /// * `option` - The server option.
+    ///
+    /// # Returns
+    ///
+    /// * `Endpoints` - The addresses, the first one in use.
+    pub(crate) fn new(option: &ServerOption) -> Endpoints {
+        let list = std::iter::once(&option.addr)
+            .chain(option.endpoints.iter())
+            .map(|addr| Endpoint {
+                addr: addr.clone(),
+                failures: 0,
+                down_until: None,
+                session_id: 0,
+                resume_token: Vec::new(),
+            })
+            .collect();
+        Endpoints {
+            list,
+            strategy: option.balance,
+            retry: Duration::from_millis(option.endpoint_retry_ms as u64),
+            next: 0,
+            current: 0,
+        }
+    }
+
+    /// Gets the address in use.
+    #[inline]
+    pub(crate) fn get_addr(&self) -> &str {
+        &self.list[self.current].addr
+    }
+
+    /// Orders the addresses to try for the next connect.
+    ///
+    /// Healthy addresses come first in the order of the strategy,
+    /// the failed ones follow so a connect is still tried when all failed.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<usize>` - The indices of the addresses.
+    pub(crate) fn candidates(&mut self) -> Vec<usize> {
+        let len = self.list.len();
+        let start = match self.strategy {
+            BalanceStrategy::Priority => 0,
+            BalanceStrategy::RoundRobin => {
+                let start = self.next % len;
+                self.next = (start + 1) % len;
+                start
+            }
+            BalanceStrategy::Random => getrandom::u64().unwrap_or_default() as usize % len,
+        };
+        let now = Instant::now();
+        let (mut healthy, failed): (Vec<usize>, Vec<usize>) = (0..len)
+            .map(|i| (start + i) % len)
+            .partition(|index| self.list[*index].is_healthy(now));
+        healthy.extend(failed);
+        healthy
+    }
+
+    /// Switches the address in use, keeping the session of each address apart.
+    ///
+    /// # Parameters
+    ///
+    /// * `index` - The index of the address to use.
+    /// * `session_id` - The session ID of the address in use.
+    /// * `resume_token` - The resume token of the address in use.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(i64, Vec<u8>)>` - The session of the new address, `None` if it is already in use.
+    pub(crate) fn select(
+        &mut self,
+        index: usize,
+        session_id: i64,
+        resume_token: Vec<u8>,
+    ) -> Option<(i64, Vec<u8>)> {
+        if index == self.current {
+            return None;
+        }
+        let current = &mut self.list[self.current];
+        current.session_id = session_id;
+        current.resume_token = resume_token;
+        self.current = index;
+        let selected = &mut self.list[index];
+        Some((
+            selected.session_id,
+            std::mem::take(&mut selected.resume_token),
+        ))
+    }
+
+    /// Marks the address in use as healthy.
+    #[inline]
+    pub(crate) fn mark_success(&mut self) {
+        let current = &mut self.list[self.current];
+        current.failures = 0;
+        current.down_until = None;
+    }
+
+    /// Marks the address in use as failed, it is tried last until the retry time passed.
+    #[inline]
+    pub(crate) fn mark_failure(&mut self) {
+        let current = &mut self.list[self.current];
+        current.failures += 1;
+        current.down_until = Some(Instant::now() + self.retry);
+    }
+
+    /// Gets the health of the addresses.
+    pub(crate) fn get_stats(&self) -> Vec<EndpointStats> {
+        let now = Instant::now();
+        self.list
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| EndpointStats {
+                addr: endpoint.addr.clone(),
+                healthy: endpoint.is_healthy(now),
+                failures: endpoint.failures,
+                current: index == self.current,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_endpoints(strategy: BalanceStrategy) -> Endpoints {
+        let mut option = ServerOption::new("a:1".into(), "test".into(), "123".into(), 5000);
+        option.endpoints = vec!["b:1".into(), "c:1".into()];
+        option.balance = strategy;
+        option.endpoint_retry_ms = 60_000;
+        Endpoints::new(&option)
+    }
+
+    fn healthy(endpoints: &Endpoints) -> Vec<bool> {
+        endpoints
+            .get_stats()
+            .iter()
+            .map(|stats| stats.healthy)
+            .collect()
+    }
+
+    #[test]
+    fn priority_keeps_the_order() {
+        let mut endpoints = new_endpoints(BalanceStrategy::Priority);
+        assert_eq!(endpoints.get_addr(), "a:1");
+        assert_eq!(endpoints.candidates(), vec![0, 1, 2]);
+        assert_eq!(endpoints.candidates(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn round_robin_rotates_the_start() {
+        let mut endpoints = new_endpoints(BalanceStrategy::RoundRobin);
+        assert_eq!(endpoints.candidates(), vec![0, 1, 2]);
+        assert_eq!(endpoints.candidates(), vec![1, 2, 0]);
+        assert_eq!(endpoints.candidates(), vec![2, 0, 1]);
+        assert_eq!(endpoints.candidates(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn random_tries_every_address_once() {
+        let mut endpoints = new_endpoints(BalanceStrategy::Random);
+        for _ in 0..16 {
+            let candidates = endpoints.candidates();
+            let start = candidates[0];
+            let expected = (0..3).map(|i| (start + i) % 3).collect::<Vec<_>>();
+            assert_eq!(candidates, expected);
+        }
+    }
+
+    #[test]
+    fn failed_addresses_are_tried_last_until_success() {
+        let mut endpoints = new_endpoints(BalanceStrategy::Priority);
+        endpoints.mark_failure();
+        endpoints.mark_failure();
+        assert_eq!(healthy(&endpoints), vec![false, true, true]);
+        assert_eq!(endpoints.get_stats()[0].failures, 2);
+        assert_eq!(endpoints.candidates(), vec![1, 2, 0]);
+
+        endpoints.select(1, 0, Vec::new());
+        endpoints.mark_failure();
+        assert_eq!(endpoints.candidates(), vec![2, 0, 1]);
+
+        endpoints.select(0, 0, Vec::new());
+        endpoints.mark_success();
+        assert_eq!(healthy(&endpoints), vec![true, false, true]);
+        assert_eq!(endpoints.get_stats()[0].failures, 0);
+        assert_eq!(endpoints.candidates(), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn failed_addresses_recover_after_retry_time() {
+        let mut option = ServerOption::new("a:1".into(), "test".into(), "123".into(), 5000);
+        option.endpoints = vec!["b:1".into()];
+        option.endpoint_retry_ms = 0;
+        let mut endpoints = Endpoints::new(&option);
+        endpoints.mark_failure();
+        assert_eq!(healthy(&endpoints), vec![true, true]);
+        assert_eq!(endpoints.candidates(), vec![0, 1]);
+    }
+
+    #[test]
+    fn select_keeps_the_session_of_each_address() {
+        let mut endpoints = new_endpoints(BalanceStrategy::Priority);
+        assert_eq!(endpoints.select(0, 10, vec![1]), None);
+        assert_eq!(endpoints.select(1, 10, vec![1]), Some((0, Vec::new())));
+        assert_eq!(endpoints.get_addr(), "b:1");
+        assert!(endpoints.get_stats()[1].current);
+        assert_eq!(endpoints.select(0, 20, vec![2]), Some((10, vec![1])));
+        assert_eq!(endpoints.select(1, 10, vec![1]), Some((20, vec![2])));
+    }
+}
@@ -5,7 +5,7 @@ use log::warn;
 use once_cell::sync::OnceCell;
 use oneshot::{channel as oneshot, Receiver, Sender};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, ReadHalf};
@@ -19,28 +19,34 @@ use tcpclient::{SocketClientTrait, TcpClient};
 use tcp_channel_client::TcpClient;
 
 use crate::client::controller::IController;
+use crate::client::endpoint::{BalanceStrategy, EndpointStats, Endpoints};
 use crate::client::maybe_stream::MaybeStream;
 use crate::client::request_manager::{IRequestManager, RequestManager};
 use crate::client::result::RetResult;
 use crate::client::NetxClientArc;
 
-cfg_if::cfg_if! {
-if #[cfg(feature = "use_openssl")]{
-   use openssl::ssl::SslConnector;
-   use tokio_openssl::SslStream;
-   use std::pin::Pin;
-}else if #[cfg(feature = "use_rustls")]{
-   use tokio_rustls::TlsConnector;
-   use tokio_rustls::rustls::pki_types::ServerName;
-}}
+#[cfg(feature = "use_openssl")]
+use openssl::ssl::SslConnector;
+#[cfg(feature = "use_openssl")]
+use std::pin::Pin;
+#[cfg(feature = "use_openssl")]
+use tokio_openssl::SslStream;
+
+#[cfg(feature = "use_rustls")]
+use tokio_rustls::rustls::pki_types::ServerName;
+#[cfg(feature = "use_rustls")]
+use tokio_rustls::TlsConnector;
 
 /// Configuration for TLS (Transport Layer Security).
+///
+/// Every backend enabled by cargo features is available at the same time,
+/// the backend used by a client is chosen when the client is created.
 #[derive(Clone)]
 pub enum TlsConfig {
     /// No TLS configuration.
     None,
     /// OpenSSL TLS configuration.
-    #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+    #[cfg(feature = "use_openssl")]
     OpenSsl {
         /// The domain name for the TLS connection.
         domain: String,
@@ -48,7 +54,7 @@ pub enum TlsConfig {
         connector: SslConnector,
     },
     /// Rustls TLS configuration.
-    #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+    #[cfg(feature = "use_rustls")]
     Rustls {
         /// The domain name for the TLS connection.
         domain: ServerName<'static>,
@@ -89,12 +95,19 @@ pub struct NetXClient<T> {
     request_manager: OnceCell<Arc<Actor<RequestManager<T>>>>,
     /// Optional controller for handling special functions.
     controller: Option<Box<dyn IController>>,
+    /// Topics subscribed to, subscribed again after every reconnect.
+    topics: HashSet<String>,
+    /// The server addresses with their health and sessions.
+    endpoints: Endpoints,
 }
 
 /// Trait for session management.
 ///
-/// This trait defines methods for getting and storing session IDs,
+/// This trait defines methods for getting and storing session IDs and resume tokens,
 /// which are used to manage individual sessions within the network client.
+///
+/// Since 3.0 the handshake sends the resume token after a non-zero session ID,
+/// servers and clients before 3.0 can not resume sessions with each other.
 pub trait SessionSave {
     /// Gets the current session ID.
     ///
@@ -109,6 +122,30 @@ pub trait SessionSave {
     ///
     /// * `session_id` - The session ID to store.
     fn store_session_id(&mut self, session_id: i64);
+
+    /// Gets the secret issued by the server to resume the session.
+    ///
+    /// The default implementation returns an empty token,
+    /// the server then starts a new session on every reconnect.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The resume token, empty if none is stored.
+    #[inline]
+    fn get_resume_token(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Stores the secret issued by the server to resume the session.
+    /// Without it a non-zero session ID is not accepted by the server.
+    ///
+    /// The default implementation does not store it.
+    ///
+    /// # Parameters
+    ///
+    /// * `resume_token` - The resume token to store.
+    #[inline]
+    fn store_resume_token(&mut self, _resume_token: Vec<u8>) {}
 }
 
 /// Tags for special functions that can be called by the network client.
@@ -119,6 +156,10 @@ enum SpecialFunctionTag {
     Disconnect = 2147483646,
     /// Tag for the closed function.
     Closed = 2147483645,
+    /// Tag for subscribing to a topic, handled by the server.
+    Subscribe = 2147483644,
+    /// Tag for unsubscribing from a topic, handled by the server.
+    Unsubscribe = 2147483643,
 }
 
 /// `Send` implementation for `NetXClient`.
@@ -146,6 +187,21 @@ pub struct ServerOption {
     pub verify_key: String,
     /// The timeout for requests in milliseconds.
     pub request_out_time_ms: u32,
+    /// More addresses of the same service, tried when connecting to `addr` fails.
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    /// How the address to connect to is chosen among `addr` and `endpoints`.
+    #[serde(default)]
+    pub balance: BalanceStrategy,
+    /// How long in milliseconds an address is tried last after connecting to it failed.
+    #[serde(default = "default_endpoint_retry_ms")]
+    pub endpoint_retry_ms: u32,
+}
+
+/// Default value for `ServerOption::endpoint_retry_ms`.
+#[inline]
+fn default_endpoint_retry_ms() -> u32 {
+    10000
 }
 
 /// Implementation of the `Display` trait for `ServerOption`.
@@ -183,85 +239,64 @@ impl ServerOption {
             service_name,
             verify_key,
             request_out_time_ms,
+            endpoints: Vec::new(),
+            balance: BalanceStrategy::default(),
+            endpoint_retry_ms: default_endpoint_retry_ms(),
         }
     }
 }
 
 impl<T: SessionSave + 'static> NetXClient<T> {
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "use_openssl")] {
-            /// Creates a new `NetXClient` with OpenSSL TLS configuration.
-            ///
-            /// # Parameters
-            ///
-            /// * `server_info` - Configuration options for the server.
-            /// * `session` - The session information.
-            /// * `domain` - The domain name for the TLS connection.
-            /// * `connector` - The OpenSSL connector.
-            ///
-            /// # Returns
-            ///
-            /// * `NetxClientArc<T>` - A new instance of `NetXClient` wrapped in an `Arc`.
-            pub fn new_ssl(server_info: ServerOption, session:T,domain:String,connector:SslConnector) ->NetxClientArc<T>{
-                let request_out_time_ms=server_info.request_out_time_ms;
-                let netx_client=Arc::new(Actor::new(NetXClient{
-                    tls_config:TlsConfig::OpenSsl {domain,connector},
-                    session,
-                    server_info,
-                    net:None,
-                    result_dict:HashMap::new(),
-                    connect_stats:None,
-                    serial_atomic:AtomicI64::new(1),
-                    request_manager:OnceCell::new(),
-                    controller:None,
-                    mode:0
-                }));
-
-                let request_manager=RequestManager::new(request_out_time_ms,Arc::downgrade(&netx_client));
-                unsafe {
-                    if netx_client.deref_inner().request_manager.set(request_manager).is_err(){
-                        log::error!("not set request_manager,request_manager may not be none")
-                    }
-                }
-                netx_client
-            }
-        } else if #[cfg(feature = "use_rustls")] {
-             /// Creates a new `NetXClient` with Rustls TLS configuration.
-             ///
-             /// # Parameters
-             ///
-             /// * `server_info` - Configuration options for the server.
-             /// * `session` - The session information.
-             /// * `domain` - The domain name for the TLS connection.
-             /// * `connector` - The Rustls connector.
-             ///
-             /// # Returns
-             ///
-             /// * `NetxClientArc<T>` - A new instance of `NetXClient` wrapped in an `Arc`.
-             pub fn new_tls(server_info: ServerOption, session:T,domain:ServerName<'static>,connector:TlsConnector) ->NetxClientArc<T>{
-                let request_out_time_ms=server_info.request_out_time_ms;
-                let netx_client=Arc::new(Actor::new(NetXClient{
-                    tls_config:TlsConfig::Rustls {domain,connector},
-                    session,
-                    server_info,
-                    net:None,
-                    result_dict:HashMap::new(),
-                    connect_stats:None,
-                    serial_atomic:AtomicI64::new(1),
-                    request_manager:OnceCell::new(),
-                    controller:None,
-                    mode:0
-                }));
-
-                let request_manager=RequestManager::new(request_out_time_ms,Arc::downgrade(&netx_client));
-                unsafe {
-                    if netx_client.deref_inner().request_manager.set(request_manager).is_err(){
-                        log::error!("not set request_manager,request_manager may not be none")
-                    }
-                }
-                netx_client
-            }
-        }
+    /// Creates a new `NetXClient` with OpenSSL TLS configuration.
+    ///
+    /// # Parameters
+    ///
+    /// * `server_info` - Configuration options for the server.
+    /// * `session` - The session information.
+    /// * `domain` - The domain name for the TLS connection.
+    /// * `connector` - The OpenSSL connector.
+    ///
+    /// # Returns
+    ///
+    /// * `NetxClientArc<T>` - A new instance of `NetXClient` wrapped in an `Arc`.
+    #[cfg(feature = "use_openssl")]
+    pub fn new_ssl(
+        server_info: ServerOption,
+        session: T,
+        domain: String,
+        connector: SslConnector,
+    ) -> NetxClientArc<T> {
+        Self::new_with_tls(
+            server_info,
+            session,
+            TlsConfig::OpenSsl { domain, connector },
+        )
+    }
+
+    /// Creates a new `NetXClient` with Rustls TLS configuration.
+    ///
+    /// # Parameters
+    ///
+    /// * `server_info` - Configuration options for the server.
+    /// * `session` - The session information.
+    /// * `domain` - The domain name for the TLS connection.
+    /// * `connector` - The Rustls connector.
+    ///
+    /// # Returns
+    ///
+    /// * `NetxClientArc<T>` - A new instance of `NetXClient` wrapped in an `Arc`.
+    #[cfg(feature = "use_rustls")]
+    pub fn new_tls(
+        server_info: ServerOption,
+        session: T,
+        domain: ServerName<'static>,
+        connector: TlsConnector,
+    ) -> NetxClientArc<T> {
+        Self::new_with_tls(
+            server_info,
+            session,
+            TlsConfig::Rustls { domain, connector },
+        )
     }
 
     /// Creates a new `NetXClient` without TLS configuration.
@@ -275,9 +310,29 @@ impl<T: SessionSave + 'static> NetXClient<T> {
     ///
     /// * `NetxClientArc<T>` - A new instance of `NetXClient` wrapped in an `Arc`.
     pub fn new(server_info: ServerOption, session: T) -> NetxClientArc<T> {
+        Self::new_with_tls(server_info, session, TlsConfig::None)
+    }
+
+    /// Creates a new `NetXClient` with the given TLS configuration.
+    ///
+    /// # Parameters
+    ///
+    /// * `server_info` - Configuration options for the server.
+    /// * `session` - The session information.
+    /// * `tls_config` - The TLS configuration, selecting plain TCP, OpenSSL or Rustls.
+    ///
+    /// # Returns
+    ///
+    /// * `NetxClientArc<T>` - A new instance of `NetXClient` wrapped in an `Arc`.
+    pub fn new_with_tls(
+        server_info: ServerOption,
+        session: T,
+        tls_config: TlsConfig,
+    ) -> NetxClientArc<T> {
         let request_out_time_ms = server_info.request_out_time_ms;
+        let endpoints = Endpoints::new(&server_info);
         let netx_client = Arc::new(Actor::new(NetXClient {
-            tls_config: TlsConfig::None,
+            tls_config,
             session,
             server_info,
             net: None,
@@ -287,6 +342,8 @@ impl<T: SessionSave + 'static> NetXClient<T> {
             request_manager: OnceCell::new(),
             controller: None,
             mode: 0,
+            topics: HashSet::new(),
+            endpoints,
         }));
 
         let request_manager =
@@ -377,6 +434,7 @@ impl<T: SessionSave + 'static> NetXClient<T> {
                     &server_info.service_name,
                     &server_info.verify_key,
                     &session_id,
+                    &netx_client.get_resume_token(),
                 )
                 .into_inner(),
             )
@@ -403,6 +461,25 @@ impl<T: SessionSave + 'static> NetXClient<T> {
                             )
                             .await?;
 
+                        // restore the subscriptions, the session may be new
+                        for topic in netx_client.get_topics().await {
+                            let data = Self::get_topic_buff(
+                                0,
+                                SpecialFunctionTag::Subscribe,
+                                netx_client.new_serial(),
+                                &topic,
+                            )?;
+                            if netx_client.get_mode() == 0 {
+                                client.send_all(data.into_inner()).await?;
+                            } else {
+                                let len = data.len() + 4;
+                                let mut buff = Data::with_capacity(len);
+                                buff.write_fixed(len as u32);
+                                buff.write_buf(&data);
+                                client.send_all(buff.into_inner()).await?;
+                            }
+                        }
+
                         // call connect if error disconnect
                         if let Some(set_connect) = option_connect.take() {
                             let client = client.clone();
@@ -443,6 +520,11 @@ impl<T: SessionSave + 'static> NetXClient<T> {
                     session_id = dr.read_fixed::<i64>()?;
                     log::debug!("{} save session id:{}", server_info, session_id);
                     netx_client.store_session_id(session_id).await;
+                    if dr.get_offset() < dr.len() {
+                        netx_client
+                            .store_resume_token(dr.read_fixed_buf()?.to_vec())
+                            .await;
+                    }
                 }
                 2400 => {
                     let tt = dr.read_fixed::<u8>()?;
@@ -504,6 +586,14 @@ impl<T: SessionSave + 'static> NetXClient<T> {
                     let serial = dr.read_fixed::<i64>()?;
                     netx_client.set_result(serial, dr).await;
                 }
+                2700 => {
+                    let count = dr.read_fixed::<u32>()?;
+                    for _ in 0..count {
+                        let mut result = DataOwnedReader::new(dr.read_fixed_buf()?.to_vec());
+                        let serial = result.read_fixed::<i64>()?;
+                        netx_client.set_result(serial, result).await;
+                    }
+                }
                 _ => {
                     log::error!("{} Unknown command:{}->{:?}", server_info, cmd, dr);
                     break;
@@ -514,6 +604,108 @@ impl<T: SessionSave + 'static> NetXClient<T> {
         Ok(())
     }
 
+    /// Connects to a server address, or waits for the connect in progress.
+    ///
+    /// # Parameters
+    ///
+    /// * `client` - The `NetxClientArc` to connect.
+    /// * `index` - The index of the server address, ignored when a connect is in progress.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Returns `Ok(())` if the handshake succeeded, otherwise returns an error.
+    async fn connect_endpoint(client: &NetxClientArc<T>, index: usize) -> crate::error::Result<()> {
+        let netx_client = client.clone();
+        let wait_handler: crate::error::Result<Option<WReceiver<(bool, String)>>> = client
+            .inner_call(|inner| async move {
+                if inner.get().is_connect() {
+                    return match inner.get().connect_stats {
+                        Some(ref stats) => Ok(Some(stats.clone())),
+                        None => {
+                            warn!("inner is connect,but not get stats");
+                            Ok(None)
+                        }
+                    };
+                }
+
+                inner.get_mut().select_endpoint(index);
+                let (set_connect, wait_connect) = channel((false, "not connect".to_string()));
+
+                let timeout = Duration::from_millis(client.get_timeout_ms() as u64);
+                let client = match netx_client.get_tls_config() {
+                    #[cfg(feature = "use_openssl")]
+                    TlsConfig::OpenSsl { domain, connector } => {
+                        let ssl = connector.configure()?.into_ssl(&domain)?;
+                        tokio::time::timeout(
+                            timeout,
+                            TcpClient::connect_stream_type(
+                                netx_client.get_address(),
+                                |tcp_stream| async move {
+                                    let mut stream = SslStream::new(ssl, tcp_stream)?;
+                                    Pin::new(&mut stream).connect().await?;
+                                    Ok(MaybeStream::ServerSsl(stream))
+                                },
+                                NetXClient::input_buffer,
+                                (netx_client, set_connect),
+                            ),
+                        )
+                        .await
+                        .map_err(|_| anyhow!("connect timeout"))??
+                    }
+                    #[cfg(feature = "use_rustls")]
+                    TlsConfig::Rustls { domain, connector } => tokio::time::timeout(
+                        timeout,
+                        TcpClient::connect_stream_type(
+                            netx_client.get_address(),
+                            |tcp_stream| async move {
+                                let stream = connector.connect(domain, tcp_stream).await?;
+                                Ok(MaybeStream::ServerTls(stream))
+                            },
+                            NetXClient::input_buffer,
+                            (netx_client, set_connect),
+                        ),
+                    )
+                    .await
+                    .map_err(|_| anyhow!("connect timeout"))??,
+                    TlsConfig::None => tokio::time::timeout(
+                        timeout,
+                        TcpClient::connect_stream_type(
+                            netx_client.get_address(),
+                            |tcp_stream| async move { Ok(MaybeStream::Plain(tcp_stream)) },
+                            NetXClient::input_buffer,
+                            (netx_client, set_connect),
+                        ),
+                    )
+                    .await
+                    .map_err(|_| anyhow!("connect timeout"))??,
+                };
+
+                let ref_inner = inner.get_mut();
+                ref_inner.set_network_client(client);
+                ref_inner.connect_stats = Some(wait_connect.clone());
+                Ok(Some(wait_connect))
+            })
+            .await;
+
+        if let Some(mut wait_handler) = wait_handler? {
+            match wait_handler.changed().await {
+                Err(err) => {
+                    client.reset_connect_stats().await;
+                    return Err(err.into());
+                }
+                Ok(_) => {
+                    client.reset_connect_stats().await;
+                    let (is_connect, msg) = &(*wait_handler.borrow());
+                    if !is_connect {
+                        return Err(crate::error::Error::ConnectError(msg.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Calls a special function on the controller if it exists.
     ///
     /// # Parameters
@@ -564,17 +756,26 @@ impl<T: SessionSave + 'static> NetXClient<T> {
     /// * `service_name` - The name of the service.
     /// * `verify_key` - The key used for verification.
     /// * `session_id` - The session ID.
+    /// * `resume_token` - The secret required to resume a non-zero session ID.
     ///
     /// # Returns
     ///
     /// * `Data` - The verification buffer.
     #[inline]
-    fn get_verify_buff(service_name: &str, verify_key: &str, session_id: &i64) -> Data {
+    fn get_verify_buff(
+        service_name: &str,
+        verify_key: &str,
+        session_id: &i64,
+        resume_token: &[u8],
+    ) -> Data {
         let mut data = Data::with_capacity(128);
         data.write_fixed(1000);
         data.write_fixed(service_name);
         data.write_fixed(verify_key);
         data.write_fixed(session_id);
+        if *session_id != 0 {
+            data.write_fixed(resume_token);
+        }
         data
     }
 
@@ -601,6 +802,30 @@ impl<T: SessionSave + 'static> NetXClient<T> {
         }
     }
 
+    /// Generates a buffer subscribing to or unsubscribing from a topic.
+    ///
+    /// # Parameters
+    ///
+    /// * `tt` - The call type, `0` to not wait for the result.
+    /// * `cmd` - `SpecialFunctionTag::Subscribe` or `SpecialFunctionTag::Unsubscribe`.
+    /// * `serial` - The serial ID.
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Data>` - The topic buffer, without the length prefix.
+    #[inline]
+    fn get_topic_buff(tt: u8, cmd: SpecialFunctionTag, serial: i64, topic: &str) -> Result<Data> {
+        let mut data = Data::with_capacity(64);
+        data.write_fixed(2400u32);
+        data.write_fixed(tt);
+        data.write_fixed(cmd as i32);
+        data.write_fixed(serial);
+        data.write_fixed(1i32);
+        data.pack_serialize(topic)?;
+        Ok(data)
+    }
+
     /// Generates a result buffer.
     ///
     /// # Parameters
@@ -660,14 +885,31 @@ impl<T: SessionSave + 'static> NetXClient<T> {
         self.mode
     }
 
-    /// Gets the address of the server as a string.
+    /// Gets the address of the server in use as a string.
     ///
     /// # Returns
     ///
     /// * `String` - The server address.
     #[inline]
     pub fn get_addr_string(&self) -> String {
-        self.server_info.addr.clone()
+        self.endpoints.get_addr().to_string()
+    }
+
+    /// Switches to a server address, storing the session of that address in the session store.
+    ///
+    /// # Parameters
+    ///
+    /// * `index` - The index of the address.
+    #[inline]
+    fn select_endpoint(&mut self, index: usize) {
+        let session_id = self.session.get_session_id();
+        let resume_token = self.session.get_resume_token();
+        if let Some((session_id, resume_token)) =
+            self.endpoints.select(index, session_id, resume_token)
+        {
+            self.session.store_session_id(session_id);
+            self.session.store_resume_token(resume_token);
+        }
     }
 
     /// Gets the service information of the server.
@@ -700,6 +942,26 @@ impl<T: SessionSave + 'static> NetXClient<T> {
         self.session.store_session_id(session_id)
     }
 
+    /// Gets the secret issued by the server to resume the session.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The resume token.
+    #[inline]
+    pub fn get_resume_token(&self) -> Vec<u8> {
+        self.session.get_resume_token()
+    }
+
+    /// Stores the secret issued by the server to resume the session.
+    ///
+    /// # Parameters
+    ///
+    /// * `resume_token` - The resume token to store.
+    #[inline]
+    pub fn store_resume_token(&mut self, resume_token: Vec<u8>) {
+        self.session.store_resume_token(resume_token)
+    }
+
     /// Sets the network client.
     ///
     /// # Parameters
@@ -790,6 +1052,20 @@ pub(crate) trait INextClientInner {
     /// - `err`: The error to be set as the result.
     async fn set_error(&self, serial: i64, err: crate::error::Error);
 
+    /// Registers the sender of a request result and the request timeout.
+    ///
+    /// # Parameters
+    /// - `serial`: The serial number of the request.
+    /// - `tx`: The sender the result is set to.
+    ///
+    /// # Returns
+    /// - `Result<()>`: Returns an error if not connected or the serial is in use.
+    async fn insert_result(
+        &self,
+        serial: i64,
+        tx: Sender<crate::error::Result<DataOwnedReader>>,
+    ) -> crate::error::Result<()>;
+
     /// Calls a special function (disconnect or connect command).
     ///
     /// # Parameters
@@ -830,6 +1106,19 @@ pub(crate) trait INextClientInner {
     /// # Parameters
     /// - `session_id`: The session ID to store.
     async fn store_session_id(&self, session_id: i64);
+
+    /// Stores the session resume token.
+    ///
+    /// # Parameters
+    /// - `resume_token`: The resume token to store.
+    async fn store_resume_token(&self, resume_token: Vec<u8>);
+
+    /// Gets the subscribed topics.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<String>` - The topic names.
+    async fn get_topics(&self) -> Vec<String>;
 }
 
 /// Implementation of the `INextClientInner` trait for `Actor<NetXClient<T>>`.
@@ -877,6 +1166,26 @@ impl<T: SessionSave + 'static> INextClientInner for Actor<NetXClient<T>> {
         }
     }
 
+    #[inline]
+    async fn insert_result(
+        &self,
+        serial: i64,
+        tx: Sender<crate::error::Result<DataOwnedReader>>,
+    ) -> crate::error::Result<()> {
+        self.inner_call(|inner| async move {
+            if inner.get().net.is_none() {
+                bail!("not connect")
+            }
+            if inner.get_mut().result_dict.contains_key(&serial) {
+                bail!("serial is have")
+            }
+            inner.get_mut().result_dict.insert(serial, tx);
+            Ok(())
+        })
+        .await?;
+        unsafe { self.deref_inner().set_request_session_id(serial).await }
+    }
+
     #[inline]
     async fn call_special_function(&self, cmd_tag: i32) -> Result<()> {
         unsafe { self.deref_inner().call_special_function(cmd_tag).await }
@@ -933,6 +1242,20 @@ impl<T: SessionSave + 'static> INextClientInner for Actor<NetXClient<T>> {
         })
         .await
     }
+
+    #[inline]
+    async fn store_resume_token(&self, resume_token: Vec<u8>) {
+        self.inner_call(|inner| async move {
+            inner.get_mut().store_resume_token(resume_token);
+        })
+        .await
+    }
+
+    #[inline]
+    async fn get_topics(&self) -> Vec<String> {
+        self.inner_call(|inner| async move { inner.get().topics.iter().cloned().collect() })
+            .await
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -981,6 +1304,12 @@ pub trait INetXClient {
     /// The session ID as an `i64`.
     fn get_session_id(&self) -> i64;
 
+    /// Gets the NetX session resume token.
+    ///
+    /// # Returns
+    /// The resume token as a `Vec<u8>`.
+    fn get_resume_token(&self) -> Vec<u8>;
+
     /// Gets the NetX mode.
     ///
     /// # Returns
@@ -1048,6 +1377,42 @@ pub trait INetXClient {
     /// # Returns
     /// A future that resolves to a `Result<()>`.
     fn run(&self, buff: Data) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Gets the health of the server addresses, `addr` first followed by `endpoints`.
+    ///
+    /// # Returns
+    /// A future that resolves to a `Vec<EndpointStats>`.
+    fn get_endpoint_stats(&self) -> impl std::future::Future<Output = Vec<EndpointStats>>;
+
+    /// Subscribes to a topic published by the server.
+    ///
+    /// Published messages arrive as calls to the controller, with the command tag
+    /// chosen by the publisher. The subscription is restored after a reconnect
+    /// once the server accepted it.
+    ///
+    /// # Parameters
+    /// - `topic`: The topic name.
+    ///
+    /// # Returns
+    /// A future that resolves to a `Result<()>`.
+    fn subscribe(
+        self: &Arc<Self>,
+        topic: &str,
+    ) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Unsubscribes from a topic.
+    ///
+    /// The subscription is still restored after a reconnect until the server acknowledged it.
+    ///
+    /// # Parameters
+    /// - `topic`: The topic name.
+    ///
+    /// # Returns
+    /// A future that resolves to a `Result<()>`.
+    fn unsubscribe(
+        self: &Arc<Self>,
+        topic: &str,
+    ) -> impl std::future::Future<Output = crate::error::Result<()>>;
 }
 
 /// Implementation of the `INetXClient` trait for `Actor<NetXClient<T>>`.
@@ -1068,78 +1433,32 @@ impl<T: SessionSave + 'static> INetXClient for Actor<NetXClient<T>> {
 
     #[inline]
     async fn connect_network(self: &Arc<Self>) -> crate::error::Result<()> {
-        let netx_client = self.clone();
-        let wait_handler: crate::error::Result<Option<WReceiver<(bool, String)>>> = self.inner_call(|inner|async move  {
-            if inner.get().is_connect() {
-                return match inner.get().connect_stats {
-                    Some(ref stats) => {
-                        Ok(Some(stats.clone()))
-                    },
-                    None => {
-                        warn!("inner is connect,but not get stats");
-                        Ok(None)
-                    }
-                }
-            }
-
-            let (set_connect, wait_connect) = channel((false, "not connect".to_string()));
-
-            let client={
-            cfg_if::cfg_if! {
-            if #[cfg(feature = "use_openssl")]{
-                if let TlsConfig::OpenSsl{domain,connector}=netx_client.get_tls_config(){
-                      let ssl=connector.configure()?.into_ssl(&domain)?;
-                      tokio::time::timeout(Duration::from_millis(self.get_timeout_ms() as u64),TcpClient::connect_stream_type(netx_client.get_address(),|tcp_stream| async move{
-                         let mut stream = SslStream::new(ssl, tcp_stream)?;
-                         Pin::new(&mut stream).connect().await?;
-                         Ok(MaybeStream::ServerSsl(stream))
-                      },NetXClient::input_buffer, (netx_client, set_connect))).await.map_err(|_|anyhow!("connect timeout"))??
-                }else{
-                      tokio::time::timeout(Duration::from_millis(self.get_timeout_ms() as u64),TcpClient::connect_stream_type(netx_client.get_address(), |tcp_stream| async move{
-                        Ok(MaybeStream::Plain(tcp_stream))
-                      },NetXClient::input_buffer, (netx_client, set_connect))).await.map_err(|_|anyhow!("connect timeout"))??
-                }
-            }else if #[cfg(feature = "use_rustls")]{
-                if let TlsConfig::Rustls{domain,connector}=netx_client.get_tls_config(){
-                      tokio::time::timeout(Duration::from_millis(self.get_timeout_ms() as u64),TcpClient::connect_stream_type(netx_client.get_address(),|tcp_stream| async move{
-                         let stream =connector.connect(domain,tcp_stream).await?;
-                         Ok(MaybeStream::ServerTls(stream))
-                      },NetXClient::input_buffer, (netx_client, set_connect))).await.map_err(|_|anyhow!("connect timeout"))??
-                }else{
-                      tokio::time::timeout(Duration::from_millis(self.get_timeout_ms() as u64),TcpClient::connect_stream_type(netx_client.get_address(), |tcp_stream| async move{
-                        Ok(MaybeStream::Plain(tcp_stream))
-                      },NetXClient::input_buffer, (netx_client, set_connect))).await.map_err(|_|anyhow!("connect timeout"))??
+        let candidates = self
+            .inner_call(|inner| async move { inner.get_mut().endpoints.candidates() })
+            .await;
+        let mut last_err = None;
+        for index in candidates {
+            match NetXClient::connect_endpoint(self, index).await {
+                Ok(()) => {
+                    self.inner_call(
+                        |inner| async move { inner.get_mut().endpoints.mark_success() },
+                    )
+                    .await;
+                    return Ok(());
                 }
-            }else{
-                    tokio::time::timeout(Duration::from_millis(self.get_timeout_ms() as u64),TcpClient::connect_stream_type(netx_client.get_address(), |tcp_stream| async move{
-                        Ok(MaybeStream::Plain(tcp_stream))
-                    },NetXClient::input_buffer, (netx_client, set_connect))).await.map_err(|_|anyhow!("connect timeout"))??
-            }}};
-
-            let ref_inner = inner.get_mut();
-            ref_inner.set_network_client(client);
-            ref_inner.connect_stats = Some(wait_connect.clone());
-            Ok(Some(wait_connect))
-
-        }).await;
-
-        if let Some(mut wait_handler) = wait_handler? {
-            match wait_handler.changed().await {
                 Err(err) => {
-                    self.reset_connect_stats().await;
-                    return Err(err.into());
-                }
-                Ok(_) => {
-                    self.reset_connect_stats().await;
-                    let (is_connect, msg) = &(*wait_handler.borrow());
-                    if !is_connect {
-                        return Err(crate::error::Error::ConnectError(msg.clone()));
-                    }
+                    log::warn!("connect {} error:{}", self.get_address(), err);
+                    self.inner_call(
+                        |inner| async move { inner.get_mut().endpoints.mark_failure() },
+                    )
+                    .await;
+                    // drop a connection whose handshake failed before trying the next address
+                    self.clean_connect().await?;
+                    last_err = Some(err);
                 }
             }
         }
-
-        Ok(())
+        Err(last_err.unwrap_or_else(|| crate::error::Error::ConnectError("not endpoint".into())))
     }
 
     #[inline]
@@ -1162,6 +1481,11 @@ impl<T: SessionSave + 'static> INetXClient for Actor<NetXClient<T>> {
         unsafe { self.deref_inner().get_session_id() }
     }
 
+    #[inline]
+    fn get_resume_token(&self) -> Vec<u8> {
+        unsafe { self.deref_inner().get_resume_token() }
+    }
+
     #[inline]
     fn get_mode(&self) -> u8 {
         unsafe { self.deref_inner().get_mode() }
@@ -1286,6 +1610,43 @@ impl<T: SessionSave + 'static> INetXClient for Actor<NetXClient<T>> {
 
         Ok(())
     }
+
+    #[inline]
+    async fn get_endpoint_stats(&self) -> Vec<EndpointStats> {
+        self.inner_call(|inner| async move { inner.get().endpoints.get_stats() })
+            .await
+    }
+
+    #[inline]
+    async fn subscribe(self: &Arc<Self>, topic: &str) -> crate::error::Result<()> {
+        if !self.is_connect() {
+            self.connect_network().await?;
+        }
+        let serial = self.new_serial();
+        let data =
+            NetXClient::<T>::get_topic_buff(1, SpecialFunctionTag::Subscribe, serial, topic)?;
+        self.call(serial, data).await?.check()?;
+        // only a subscription accepted by the server is restored after a reconnect
+        let name = topic.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().topics.insert(name) })
+            .await;
+        Ok(())
+    }
+
+    #[inline]
+    async fn unsubscribe(self: &Arc<Self>, topic: &str) -> crate::error::Result<()> {
+        if !self.is_connect() {
+            self.connect_network().await?;
+        }
+        let serial = self.new_serial();
+        let data =
+            NetXClient::<T>::get_topic_buff(1, SpecialFunctionTag::Unsubscribe, serial, topic)?;
+        self.call(serial, data).await?.check()?;
+        let name = topic.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().topics.remove(&name) })
+            .await;
+        Ok(())
+    }
 }
 
 #[macro_export]
@@ -1394,6 +1755,48 @@ macro_rules! call {
 
     });
 
+    // Macro to add a call to a batch, returning a `BatchResult` to deserialize the result
+    (@batch $batch:expr=>$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call!(@count $($args),*) as i32;
+            let serial=$batch.new_serial();
+            data.write_fixed(2u8);
+            data.write_fixed($cmd);
+            data.write_fixed(serial);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            $batch.add_call(serial,data)
+    });
+
+    // Macro to add a call to a batch, returning a `BatchResult` to check the result
+    (@batch_checkrun $batch:expr=>$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call!(@count $($args),*) as i32;
+            let serial=$batch.new_serial();
+            data.write_fixed(1u8);
+            data.write_fixed($cmd);
+            data.write_fixed(serial);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            $batch.add_call::<()>(serial,data)
+    });
+
+    // Macro to add a command without a result to a batch
+    (@batch_run $batch:expr=>$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call!(@count $($args),*) as i32;
+            let serial=$batch.new_serial();
+            data.write_fixed(0u8);
+            data.write_fixed($cmd);
+            data.write_fixed(serial);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            $batch.add_run(data);
+    });
+
 }
 
 /// Macro to create a `Box<dyn $interface>` that clones `$client`.
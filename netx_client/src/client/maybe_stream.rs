@@ -2,23 +2,24 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
-#[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+#[cfg(feature = "use_openssl")]
 use tokio_openssl::SslStream;
 
-#[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+#[cfg(feature = "use_rustls")]
 use tokio_rustls::client::TlsStream;
 
 /// `MaybeStream` is an enum that represents a stream which can be either a plain `TcpStream`
 /// or a TLS/SSL encrypted stream using either OpenSSL or Rustls.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum MaybeStream {
     /// A plain TCP stream.
     Plain(TcpStream),
     /// An SSL encrypted stream using OpenSSL.
-    #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+    #[cfg(feature = "use_openssl")]
     ServerSsl(SslStream<TcpStream>),
     /// A TLS encrypted stream using Rustls.
-    #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+    #[cfg(feature = "use_rustls")]
     ServerTls(TlsStream<TcpStream>),
 }
 
@@ -40,9 +41,9 @@ impl AsyncRead for MaybeStream {
     ) -> Poll<std::io::Result<()>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_read(cx, buf),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_read(cx, buf),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_read(cx, buf),
         }
     }
@@ -66,9 +67,9 @@ impl AsyncWrite for MaybeStream {
     ) -> Poll<Result<usize, std::io::Error>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_write(cx, buf),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_write(cx, buf),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_write(cx, buf),
         }
     }
@@ -85,9 +86,9 @@ impl AsyncWrite for MaybeStream {
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_flush(cx),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_flush(cx),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_flush(cx),
         }
     }
@@ -107,9 +108,9 @@ impl AsyncWrite for MaybeStream {
     ) -> Poll<Result<(), std::io::Error>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_shutdown(cx),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_shutdown(cx),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_shutdown(cx),
         }
     }
@@ -1,23 +1,44 @@
 #[macro_use]
 mod impl_client;
+mod batch;
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+mod certificate_pin;
 pub mod controller;
 mod default_session_save;
+mod endpoint;
 mod maybe_stream;
+mod pool;
 mod request_manager;
 mod result;
 #[cfg(feature = "use_rustls")]
 mod rustls_accept_any_cert_verifier;
+#[cfg(feature = "use_rustls")]
+mod rustls_pinned_cert_verifier;
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+mod tls_config_builder;
 
 use aqueue::Actor;
 use std::sync::Arc;
 
+pub use batch::{BatchResult, NetXBatch};
 pub use controller::*;
 pub use default_session_save::*;
+pub use endpoint::{BalanceStrategy, EndpointStats};
 pub use impl_client::*;
+pub use pool::{ISelectClient, NetXClientPool, PooledClient};
 pub use result::RetResult;
 
+#[cfg(feature = "use_openssl")]
+pub use certificate_pin::set_openssl_cert_pins;
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+pub use certificate_pin::CertificatePin;
+
 #[cfg(feature = "use_rustls")]
 pub use rustls_accept_any_cert_verifier::RustlsAcceptAnyCertVerifier;
+#[cfg(feature = "use_rustls")]
+pub use rustls_pinned_cert_verifier::RustlsPinnedCertVerifier;
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+pub use tls_config_builder::TlsConfigBuilder;
 
 /// Type alias for a reference-counted `Actor` wrapping a `NetXClient` with a generic session store.
 pub type NetxClientArc<T> = Arc<Actor<NetXClient<T>>>;
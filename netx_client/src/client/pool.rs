@@ -0,0 +1,292 @@
+use super::{INetXClient, NetxClientArc, SessionSave};
+use anyhow::ensure;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Selects the connection a call of a generated interface goes through.
+///
+/// Implemented by `NetxClientArc`, which always selects itself, and by `NetXClientPool`.
+pub trait ISelectClient: Send + Sync {
+    /// The session store of the connections.
+    type Session: SessionSave + 'static;
+    /// The selected connection, held until the call is done.
+    type Client: Send + Sync;
+
+    /// Selects the connection for the next call.
+    ///
+    /// # Returns
+    ///
+    /// * `Self::Client` - The selected connection.
+    fn select_client(&self) -> Self::Client;
+
+    /// Gets the client of a selected connection.
+    ///
+    /// # Parameters
+    ///
+    /// * `client` - The selected connection.
+    ///
+    /// # Returns
+    ///
+    /// * `&NetxClientArc<Self::Session>` - The client to call.
+    fn get_client(client: &Self::Client) -> &NetxClientArc<Self::Session>;
+}
+
+impl<T: SessionSave + 'static> ISelectClient for NetxClientArc<T> {
+    type Session = T;
+    type Client = NetxClientArc<T>;
+
+    #[inline]
+    fn select_client(&self) -> Self::Client {
+        self.clone()
+    }
+
+    #[inline]
+    fn get_client(client: &Self::Client) -> &NetxClientArc<T> {
+        client
+    }
+}
+
+impl<C: ISelectClient> ISelectClient for &C {
+    type Session = C::Session;
+    type Client = C::Client;
+
+    #[inline]
+    fn select_client(&self) -> Self::Client {
+        (**self).select_client()
+    }
+
+    #[inline]
+    fn get_client(client: &Self::Client) -> &NetxClientArc<Self::Session> {
+        C::get_client(client)
+    }
+}
+
+/// A connection of the pool with the number of its calls in progress.
+struct PoolEntry<T> {
+    client: NetxClientArc<T>,
+    in_flight: AtomicUsize,
+}
+
+/// A pool of `NetXClient` connections to the same service.
+///
+/// Each call goes through the connection with the fewest calls in progress,
+/// so the pool can be used in place of a single client with `impl_struct!`
+/// and `impl_interface!`. Every connection has its own session.
+pub struct NetXClientPool<T> {
+    entries: Arc<[PoolEntry<T>]>,
+    /// Where the search for the least busy connection starts, spreading calls over idle connections.
+    cursor: Arc<AtomicUsize>,
+}
+
+impl<T> Clone for NetXClientPool<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        NetXClientPool {
+            entries: self.entries.clone(),
+            cursor: self.cursor.clone(),
+        }
+    }
+}
+
+impl<T: SessionSave + 'static> NetXClientPool<T> {
+    /// Creates a pool of clients.
+    ///
+    /// The clients should be initialized with their controllers.
+    ///
+    /// # Parameters
+    ///
+    /// * `clients` - The clients of the pool, connecting to the same service.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<NetXClientPool<T>>` - The pool, or an error if `clients` is empty.
+    pub fn new(clients: Vec<NetxClientArc<T>>) -> anyhow::Result<NetXClientPool<T>> {
+        ensure!(!clients.is_empty(), "client pool is empty");
+        Ok(NetXClientPool {
+            entries: clients
+                .into_iter()
+                .map(|client| PoolEntry {
+                    client,
+                    in_flight: AtomicUsize::new(0),
+                })
+                .collect(),
+            cursor: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Gets the number of connections.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether the pool has no connections, never `true` for a created pool.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the clients of the pool.
+    #[inline]
+    pub fn get_clients(&self) -> Vec<NetxClientArc<T>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.client.clone())
+            .collect()
+    }
+
+    /// Gets the number of calls in progress on each connection.
+    #[inline]
+    pub fn get_in_flight(&self) -> Vec<usize> {
+        self.entries
+            .iter()
+            .map(|entry| entry.in_flight.load(Ordering::Acquire))
+            .collect()
+    }
+
+    /// Connects every client of the pool that is not connected.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - The first connect error, the other clients are still connected.
+    pub async fn connect_network(&self) -> crate::error::Result<()> {
+        let mut result = Ok(());
+        for entry in self.entries.iter() {
+            if !entry.client.is_connect() {
+                if let Err(err) = entry.client.connect_network().await {
+                    log::error!("pool connect {} error:{}", entry.client.get_address(), err);
+                    if result.is_ok() {
+                        result = Err(err);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Closes every client of the pool.
+    pub async fn close(&self) -> crate::error::Result<()> {
+        for entry in self.entries.iter() {
+            entry.client.close().await?;
+        }
+        Ok(())
+    }
+
+    /// Selects the connected client with the fewest calls in progress,
+    /// or any client with the fewest calls when none is connected.
+    fn select_index(&self) -> usize {
+        let len = self.entries.len();
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed) % len;
+        (0..len)
+            .map(|i| (start + i) % len)
+            .min_by_key(|index| {
+                let entry = &self.entries[*index];
+                (
+                    !entry.client.is_connect(),
+                    entry.in_flight.load(Ordering::Acquire),
+                )
+            })
+            .unwrap_or(start)
+    }
+}
+
+/// A connection selected from a `NetXClientPool`, counted as busy until dropped.
+pub struct PooledClient<T> {
+    entries: Arc<[PoolEntry<T>]>,
+    index: usize,
+}
+
+impl<T> Drop for PooledClient<T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.entries[self.index]
+            .in_flight
+            .fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<T: SessionSave + 'static> ISelectClient for NetXClientPool<T> {
+    type Session = T;
+    type Client = PooledClient<T>;
+
+    #[inline]
+    fn select_client(&self) -> Self::Client {
+        let index = self.select_index();
+        self.entries[index].in_flight.fetch_add(1, Ordering::AcqRel);
+        PooledClient {
+            entries: self.entries.clone(),
+            index,
+        }
+    }
+
+    #[inline]
+    fn get_client(client: &Self::Client) -> &NetxClientArc<T> {
+        &client.entries[client.index].client
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{DefaultSessionStore, NetXClient, ServerOption};
+
+    fn new_pool(len: usize) -> NetXClientPool<DefaultSessionStore> {
+        let clients = (0..len)
+            .map(|i| {
+                NetXClient::new(
+                    ServerOption::new(
+                        format!("127.0.0.1:{}", i + 1),
+                        "test".into(),
+                        "123".into(),
+                        5000,
+                    ),
+                    DefaultSessionStore::default(),
+                )
+            })
+            .collect();
+        NetXClientPool::new(clients).unwrap()
+    }
+
+    fn address(client: &PooledClient<DefaultSessionStore>) -> String {
+        NetXClientPool::get_client(client).get_address()
+    }
+
+    #[test]
+    fn empty_pool_is_an_error() {
+        assert!(NetXClientPool::<DefaultSessionStore>::new(Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn selects_the_least_in_flight_client() {
+        let pool = new_pool(3);
+        let first = pool.select_client();
+        let second = pool.select_client();
+        let third = pool.select_client();
+        assert_eq!(pool.get_in_flight(), vec![1, 1, 1]);
+        let mut addresses = vec![address(&first), address(&second), address(&third)];
+        addresses.sort();
+        addresses.dedup();
+        assert_eq!(addresses.len(), 3);
+
+        // the released client is the only one with no call in progress
+        let released = address(&second);
+        drop(second);
+        let next = pool.select_client();
+        assert_eq!(address(&next), released);
+
+        drop((first, third, next));
+        assert_eq!(pool.get_in_flight(), vec![0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_in_flight_counts() {
+        let pool = new_pool(2);
+        let clone = pool.clone();
+        let busy = pool.select_client();
+        let other = clone.select_client();
+        assert_ne!(address(&busy), address(&other));
+        assert_eq!(clone.get_in_flight(), vec![1, 1]);
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.get_clients().len(), 2);
+    }
+}
@@ -137,6 +137,7 @@ impl RetResult {
     ///
     /// * `index` - The index of the argument to get.
     #[inline]
+    #[allow(clippy::io_other_error)]
     pub fn get(&mut self, index: usize) -> io::Result<&mut DataOwnedReader> {
         if index >= self.len() {
             return Err(io::Error::new(ErrorKind::Other, "index >= len"));
@@ -150,6 +151,7 @@ impl RetResult {
     ///
     /// * `T` - The type to deserialize the argument into.
     #[inline]
+    #[allow(clippy::io_other_error)]
     pub fn deserialize<'a, T: Deserialize<'a> + 'static>(&'a mut self) -> crate::error::Result<T> {
         if self.is_empty() {
             return Err(io::Error::new(ErrorKind::Other, "index >= len").into());
@@ -0,0 +1,98 @@
+use crate::client::CertificatePin;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{
+    aws_lc_rs, verify_tls12_signature, verify_tls13_signature, CryptoProvider,
+};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{CertificateError, DigitallySignedStruct, Error, SignatureScheme};
+
+/// Trust a server only if its certificate or public key matches a pinned SHA-256 fingerprint.
+/// Handshake signatures are verified as usual.
+#[derive(Debug, Clone)]
+pub struct RustlsPinnedCertVerifier {
+    pins: Vec<CertificatePin>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl RustlsPinnedCertVerifier {
+    /// Creates a new `RustlsPinnedCertVerifier`.
+    ///
+    /// # Parameters
+    ///
+    /// * `pins` - The accepted fingerprints, the server leaf certificate must match one of them.
+    ///
+    /// # Returns
+    ///
+    /// * `RustlsPinnedCertVerifier` - A verifier using the process default crypto provider.
+    pub fn new(pins: Vec<CertificatePin>) -> RustlsPinnedCertVerifier {
+        let provider = CryptoProvider::get_default()
+            .cloned()
+            .unwrap_or_else(|| Arc::new(aws_lc_rs::default_provider()));
+        RustlsPinnedCertVerifier { pins, provider }
+    }
+}
+
+impl ServerCertVerifier for RustlsPinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        let (_, cert) = x509_parser::parse_x509_certificate(end_entity)
+            .map_err(|_| Error::InvalidCertificate(CertificateError::BadEncoding))?;
+        let cert_sha256 = Sha256::digest(end_entity);
+        let public_key_sha256 = Sha256::digest(cert.tbs_certificate.subject_pki.raw);
+        if self
+            .pins
+            .iter()
+            .any(|pin| pin.matches(&cert_sha256, &public_key_sha256))
+        {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
@@ -0,0 +1,217 @@
+use crate::client::{CertificatePin, TlsConfig};
+use std::path::{Path, PathBuf};
+
+/// Builds a client `TlsConfig` from PEM files.
+#[derive(Clone, Debug)]
+pub struct TlsConfigBuilder {
+    domain: String,
+    ca_path: Option<PathBuf>,
+    identity: Option<(PathBuf, PathBuf)>,
+    alpn_protocols: Vec<Vec<u8>>,
+    pins: Vec<CertificatePin>,
+    accept_invalid_certs: bool,
+}
+
+impl TlsConfigBuilder {
+    /// Creates a new `TlsConfigBuilder`.
+    ///
+    /// # Parameters
+    ///
+    /// * `domain` - The domain name used to verify the server certificate.
+    ///
+    /// # Returns
+    ///
+    /// * `TlsConfigBuilder` - A new builder without client certificate.
+    #[inline]
+    pub fn new(domain: impl Into<String>) -> TlsConfigBuilder {
+        TlsConfigBuilder {
+            domain: domain.into(),
+            ca_path: None,
+            identity: None,
+            alpn_protocols: Vec::new(),
+            pins: Vec::new(),
+            accept_invalid_certs: false,
+        }
+    }
+
+    /// Sets the PEM file containing the CA certificates used to verify the server.
+    ///
+    /// # Parameters
+    ///
+    /// * `ca_path` - The CA PEM file.
+    ///
+    /// # Returns
+    ///
+    /// * `TlsConfigBuilder` - The updated builder.
+    #[inline]
+    pub fn ca(mut self, ca_path: impl AsRef<Path>) -> TlsConfigBuilder {
+        self.ca_path = Some(ca_path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets the client certificate presented to servers requiring client authentication.
+    ///
+    /// # Parameters
+    ///
+    /// * `cert_path` - The PEM file containing the client certificate chain, leaf first.
+    /// * `key_path` - The PEM file containing the client private key.
+    ///
+    /// # Returns
+    ///
+    /// * `TlsConfigBuilder` - The updated builder.
+    #[inline]
+    pub fn identity(
+        mut self,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> TlsConfigBuilder {
+        self.identity = Some((
+            cert_path.as_ref().to_path_buf(),
+            key_path.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    /// Sets the ALPN protocols offered to the server, in order of preference.
+    ///
+    /// # Parameters
+    ///
+    /// * `protocols` - The protocol names, like `b"netx"`.
+    ///
+    /// # Returns
+    ///
+    /// * `TlsConfigBuilder` - The updated builder.
+    #[inline]
+    pub fn alpn_protocols<P: AsRef<[u8]>>(mut self, protocols: &[P]) -> TlsConfigBuilder {
+        self.alpn_protocols = protocols.iter().map(|p| p.as_ref().to_vec()).collect();
+        self
+    }
+
+    /// Accepts the server only if its certificate matches one of the pinned fingerprints.
+    /// Pinning replaces CA verification, so no CA file is needed.
+    ///
+    /// # Parameters
+    ///
+    /// * `pins` - The accepted certificate or public key fingerprints.
+    ///
+    /// # Returns
+    ///
+    /// * `TlsConfigBuilder` - The updated builder.
+    #[inline]
+    pub fn pin_certificates(mut self, pins: Vec<CertificatePin>) -> TlsConfigBuilder {
+        self.pins = pins;
+        self
+    }
+
+    /// Skips verification of the server certificate.
+    /// Please DO NOT use in production.
+    ///
+    /// # Parameters
+    ///
+    /// * `accept_invalid_certs` - Whether any server certificate is accepted.
+    ///
+    /// # Returns
+    ///
+    /// * `TlsConfigBuilder` - The updated builder.
+    #[inline]
+    pub fn danger_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> TlsConfigBuilder {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Builds an OpenSSL `TlsConfig` by reading the PEM files.
+    /// Without a CA file the system trust store is used.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<TlsConfig>` - The TLS configuration, or an error if a file cannot be loaded.
+    #[cfg(feature = "use_openssl")]
+    pub fn build_openssl(&self) -> anyhow::Result<TlsConfig> {
+        use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+
+        let mut connector = SslConnector::builder(SslMethod::tls())?;
+        if let Some(ca_path) = &self.ca_path {
+            connector.set_ca_file(ca_path)?;
+        }
+        if let Some((cert_path, key_path)) = &self.identity {
+            connector.set_private_key_file(key_path, SslFiletype::PEM)?;
+            connector.set_certificate_chain_file(cert_path)?;
+            connector.check_private_key()?;
+        }
+        if self.accept_invalid_certs {
+            connector.set_verify(SslVerifyMode::NONE);
+        } else if !self.pins.is_empty() {
+            crate::client::set_openssl_cert_pins(&mut connector, self.pins.clone());
+        } else {
+            connector.set_verify(SslVerifyMode::PEER);
+        }
+        if !self.alpn_protocols.is_empty() {
+            let mut wire = Vec::new();
+            for protocol in self.alpn_protocols.iter() {
+                wire.push(protocol.len() as u8);
+                wire.extend_from_slice(protocol);
+            }
+            connector.set_alpn_protos(&wire)?;
+        }
+
+        Ok(TlsConfig::OpenSsl {
+            domain: self.domain.clone(),
+            connector: connector.build(),
+        })
+    }
+
+    /// Builds a Rustls `TlsConfig` by reading the PEM files.
+    /// A CA file is required unless certificates are pinned or invalid certificates are accepted.
+    ///
+    /// # Returns
+    ///
+    /// * `anyhow::Result<TlsConfig>` - The TLS configuration, or an error if a file cannot be loaded.
+    #[cfg(feature = "use_rustls")]
+    pub fn build_rustls(&self) -> anyhow::Result<TlsConfig> {
+        use crate::client::{RustlsAcceptAnyCertVerifier, RustlsPinnedCertVerifier};
+        use std::convert::TryFrom;
+        use std::sync::Arc;
+        use tokio_rustls::rustls::pki_types::pem::PemObject;
+        use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+        use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+        use tokio_rustls::TlsConnector;
+
+        let builder = if self.accept_invalid_certs {
+            ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(RustlsAcceptAnyCertVerifier))
+        } else if !self.pins.is_empty() {
+            ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(RustlsPinnedCertVerifier::new(
+                    self.pins.clone(),
+                )))
+        } else {
+            let ca_path = match &self.ca_path {
+                Some(ca_path) => ca_path,
+                None => anyhow::bail!("rustls client requires a ca file or certificate pins"),
+            };
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(ca_path)? {
+                roots.add(cert?)?;
+            }
+            ClientConfig::builder().with_root_certificates(roots)
+        };
+
+        let mut tls_config = match &self.identity {
+            Some((cert_path, key_path)) => {
+                let cert_chain =
+                    CertificateDer::pem_file_iter(cert_path)?.collect::<Result<Vec<_>, _>>()?;
+                let key = PrivateKeyDer::from_pem_file(key_path)?;
+                builder.with_client_auth_cert(cert_chain, key)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        tls_config.alpn_protocols = self.alpn_protocols.clone();
+
+        Ok(TlsConfig::Rustls {
+            domain: ServerName::try_from(self.domain.clone())?,
+            connector: TlsConnector::from(Arc::new(tls_config)),
+        })
+    }
+}
@@ -36,7 +36,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
 //
 // OpenSSL example
 //
-#[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+#[cfg(feature = "use_openssl")]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     env_logger::Builder::default()
@@ -142,6 +142,7 @@ impl ITestController for TestController {
         Ok(v)
     }
 
+    #[allow(clippy::disallowed_names)]
     async fn test_struct(&self, foo: Foo) -> Result<Foo> {
         println!("{:?}", foo);
         Ok(foo)
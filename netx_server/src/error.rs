@@ -22,6 +22,10 @@ pub enum Error {
     SerialHave,
     #[error("token:{0} disconnect")]
     TokenDisconnect(i64),
+    #[error("token:{0} outbox is full")]
+    OutboxFull(i64),
+    #[error("session:{0} not found")]
+    SessionNotFound(i64),
     #[error("Call Error:{{ id:{0},msg:\"{1}\"}}")]
     CallError(i32, String),
 }
@@ -1,7 +1,13 @@
 pub use super::server::{
-    async_token_manager::ITokenManager, IAsyncToken, IController, ICreateController, NetXServer,
-    NetxToken, RetResult, ServerOption,
+    async_token_manager::ITokenManager, AuthEvent, AuthEventKind, DuplicateSessionPolicy,
+    FileSessionStore, IAsyncToken, IController, ICreateController, MemorySessionStore, NetXServer,
+    NetxToken, OutboxOverflow, OutboxStats, PeerCertificate, RetResult, ServerOption, SessionEvent,
+    SessionEventKind, SessionInfo, SessionRecord, SessionStore, TlsConfig, TlsReloadHandle,
 };
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+pub use super::server::{ClientAuth, TlsConfigBuilder};
+#[cfg(feature = "cluster")]
+pub use super::server::{Cluster, ClusterOption, ClusterPeer};
 pub use crate::error;
 pub use crate::{call_peer, impl_ref};
 pub use aqueue;
@@ -0,0 +1,289 @@
+use crate::ServerOption;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// An IP network in CIDR notation, like `10.0.0.0/8` or `::1/128`.
+#[derive(Copy, Clone, Debug)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parses a CIDR, a plain IP address is treated as a single host.
+    fn parse(cidr: &str) -> Result<Cidr> {
+        let (addr, prefix) = match cidr.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (cidr, None),
+        };
+        let addr = addr
+            .trim()
+            .parse::<IpAddr>()
+            .with_context(|| format!("invalid cidr:{}", cidr))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .with_context(|| format!("invalid cidr:{}", cidr))?,
+            None => max,
+        };
+        if prefix > max {
+            bail!("invalid cidr:{} prefix > {}", cidr, max)
+        }
+        Ok(Cidr { addr, prefix })
+    }
+
+    /// Checks whether the network contains the address.
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Open connections, in total and per IP.
+#[derive(Default)]
+struct Connections {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+/// Admission rules applied to every accepted connection.
+pub(crate) struct Admission {
+    max_connections: usize,
+    max_connections_per_ip: usize,
+    allow_list: Vec<Cidr>,
+    deny_list: Vec<Cidr>,
+    connections: Mutex<Connections>,
+    rejected: AtomicU64,
+}
+
+impl Admission {
+    /// Creates the admission rules from the server options.
+    ///
+    /// # Arguments
+    ///
+    /// * `option` - The server options.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the admission rules.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if an entry of `allow_list` or `deny_list` is not a valid CIDR.
+    pub(crate) fn new(option: &ServerOption) -> Result<Arc<Admission>> {
+        let parse = |list: &[String]| -> Result<Vec<Cidr>> {
+            list.iter().map(|cidr| Cidr::parse(cidr)).collect()
+        };
+        Ok(Arc::new(Admission {
+            max_connections: option.max_connections,
+            max_connections_per_ip: option.max_connections_per_ip,
+            allow_list: parse(&option.allow_list)?,
+            deny_list: parse(&option.deny_list)?,
+            connections: Default::default(),
+            rejected: AtomicU64::new(0),
+        }))
+    }
+
+    /// Admits a connection from the given IP address.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - The IP address of the remote client.
+    /// * `admit_callback` - The user callback, called after the built in rules pass.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a guard that releases the connection slot when dropped.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the connection is rejected.
+    pub(crate) fn admit(
+        self: &Arc<Self>,
+        ip: IpAddr,
+        admit_callback: impl FnOnce() -> bool,
+    ) -> Result<AdmissionGuard> {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            IpAddr::V4(_) => ip,
+        };
+        if let Err(err) = self.check(ip, admit_callback) {
+            let rejected = self.rejected.fetch_add(1, Ordering::Relaxed) + 1;
+            log::warn!("ip:{} rejected:{} total rejected:{}", ip, err, rejected);
+            return Err(err);
+        }
+        Ok(AdmissionGuard {
+            admission: self.clone(),
+            ip,
+        })
+    }
+
+    /// Applies the rules and reserves a connection slot.
+    fn check(&self, ip: IpAddr, admit_callback: impl FnOnce() -> bool) -> Result<()> {
+        if self.deny_list.iter().any(|cidr| cidr.contains(&ip)) {
+            bail!("in deny list")
+        }
+        if !self.allow_list.is_empty() && !self.allow_list.iter().any(|cidr| cidr.contains(&ip)) {
+            bail!("not in allow list")
+        }
+        if !admit_callback() {
+            bail!("rejected by controller")
+        }
+        let mut connections = self.connections.lock().unwrap();
+        if self.max_connections > 0 && connections.total >= self.max_connections {
+            bail!("max connections {} reached", self.max_connections)
+        }
+        let count = connections.per_ip.entry(ip).or_insert(0);
+        if self.max_connections_per_ip > 0 && *count >= self.max_connections_per_ip {
+            bail!(
+                "max connections per ip {} reached",
+                self.max_connections_per_ip
+            )
+        }
+        *count += 1;
+        connections.total += 1;
+        Ok(())
+    }
+
+    /// Gets the number of rejected connections.
+    #[inline]
+    pub(crate) fn get_rejected_count(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of admitted connections that are still open.
+    #[inline]
+    pub(crate) fn get_connection_count(&self) -> usize {
+        self.connections.lock().unwrap().total
+    }
+}
+
+/// Holds a connection slot until the connection is closed.
+pub(crate) struct AdmissionGuard {
+    admission: Arc<Admission>,
+    ip: IpAddr,
+}
+
+impl Drop for AdmissionGuard {
+    fn drop(&mut self) {
+        let mut connections = self.admission.connections.lock().unwrap();
+        connections.total -= 1;
+        if let Some(count) = connections.per_ip.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                connections.per_ip.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn cidr_parse() -> Result<()> {
+        let cidr = Cidr::parse("10.0.0.0/8")?;
+        assert_eq!((cidr.addr, cidr.prefix), (ip("10.0.0.0"), 8));
+        let cidr = Cidr::parse(" 192.168.1.7 ")?;
+        assert_eq!((cidr.addr, cidr.prefix), (ip("192.168.1.7"), 32));
+        let cidr = Cidr::parse("::1")?;
+        assert_eq!((cidr.addr, cidr.prefix), (ip("::1"), 128));
+        let cidr = Cidr::parse("2001:db8::/32")?;
+        assert_eq!((cidr.addr, cidr.prefix), (ip("2001:db8::"), 32));
+
+        for invalid in [
+            "",
+            "10.0.0.0/",
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0/8",
+            "10.0.0.0/x",
+            "host/8",
+        ] {
+            assert!(Cidr::parse(invalid).is_err(), "{}", invalid);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn cidr_contains() -> Result<()> {
+        let net = Cidr::parse("10.1.0.0/16")?;
+        assert!(net.contains(&ip("10.1.0.1")));
+        assert!(net.contains(&ip("10.1.255.255")));
+        assert!(!net.contains(&ip("10.2.0.1")));
+        assert!(!net.contains(&ip("::ffff:10.1.0.1")));
+
+        let host = Cidr::parse("192.168.1.7")?;
+        assert!(host.contains(&ip("192.168.1.7")));
+        assert!(!host.contains(&ip("192.168.1.8")));
+
+        let all = Cidr::parse("0.0.0.0/0")?;
+        assert!(all.contains(&ip("8.8.8.8")));
+        assert!(!all.contains(&ip("::1")));
+
+        let net = Cidr::parse("2001:db8::/32")?;
+        assert!(net.contains(&ip("2001:db8:ffff::1")));
+        assert!(!net.contains(&ip("2001:db9::1")));
+        assert!(Cidr::parse("::/0")?.contains(&ip("::1")));
+        Ok(())
+    }
+
+    #[test]
+    fn new_rejects_invalid_cidr_options() {
+        let mut option = ServerOption::new("127.0.0.1:0", "test", "123");
+        option.allow_list.push("10.0.0.0/40".to_string());
+        assert!(Admission::new(&option).is_err());
+
+        let mut option = ServerOption::new("127.0.0.1:0", "test", "123");
+        option.deny_list.push("bad".to_string());
+        assert!(Admission::new(&option).is_err());
+    }
+
+    #[test]
+    fn admit_applies_lists_and_limits() -> Result<()> {
+        let mut option = ServerOption::new("127.0.0.1:0", "test", "123");
+        option.allow_list.push("10.0.0.0/8".to_string());
+        option.deny_list.push("10.0.0.66".to_string());
+        option.max_connections = 3;
+        option.max_connections_per_ip = 2;
+        let admission = Admission::new(&option)?;
+
+        assert!(admission.admit(ip("10.0.0.66"), || true).is_err());
+        assert!(admission.admit(ip("192.168.0.1"), || true).is_err());
+        assert!(admission.admit(ip("10.0.0.1"), || false).is_err());
+
+        // ipv4 mapped ipv6 addresses are matched as ipv4
+        let first = admission.admit(ip("::ffff:10.0.0.1"), || true)?;
+        let second = admission.admit(ip("10.0.0.1"), || true)?;
+        assert!(admission.admit(ip("10.0.0.1"), || true).is_err());
+        let third = admission.admit(ip("10.0.0.2"), || true)?;
+        assert!(admission.admit(ip("10.0.0.3"), || true).is_err());
+        assert_eq!(admission.get_connection_count(), 3);
+        assert_eq!(admission.get_rejected_count(), 5);
+
+        drop(first);
+        let _fourth = admission.admit(ip("10.0.0.1"), || true)?;
+        drop((second, third));
+        assert_eq!(admission.get_connection_count(), 1);
+        Ok(())
+    }
+}
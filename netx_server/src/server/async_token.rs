@@ -1,21 +1,56 @@
 use crate::async_token_manager::IAsyncTokenManager;
-use crate::{IController, NetPeer, RetResult};
+use crate::extensions::Extensions;
+use crate::impl_server::SpecialFunctionTag;
+use crate::outbox::{Outbox, OutboxStats};
+use crate::session_store::SessionRecord;
+use crate::{IController, NetPeer, PeerCertificate, RetResult};
 //use anyhow::{anyhow, bail, Result};
 use aqueue::Actor;
 use data_rw::{Data, DataOwnedReader};
 use oneshot::{channel as oneshot, Receiver, Sender};
-use std::collections::{HashMap, VecDeque};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
 use tokio::time::Instant;
 
 #[cfg(all(feature = "tcpserver", not(feature = "tcp-channel-server")))]
 use tcpserver::IPeer;
 
+/// The length in bytes of the secret needed to resume a session.
+pub(crate) const RESUME_TOKEN_LEN: usize = 32;
+
+/// Compares a stored resume token with the one presented by a client in constant time.
+///
+/// # Arguments
+///
+/// * `expected` - The resume token of the session.
+/// * `presented` - The resume token sent by the client.
+///
+/// # Returns
+///
+/// `true` if both are the same resume token.
+#[inline]
+pub(crate) fn resume_token_eq(expected: &[u8], presented: &[u8]) -> bool {
+    expected.len() == RESUME_TOKEN_LEN
+        && presented.len() == RESUME_TOKEN_LEN
+        && expected
+            .iter()
+            .zip(presented)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
 /// Represents an asynchronous token that manages a session and its associated data.
 pub struct AsyncToken<T> {
     /// The session ID associated with this token.
     session_id: i64,
+    /// The secret a client must present with the session ID to resume the session.
+    resume_token: [u8; RESUME_TOKEN_LEN],
     /// The controller associated with this token, wrapped in an `Arc`.
     controller: Option<Arc<T>>,
     /// The network peer associated with this token, wrapped in an `Arc`.
@@ -28,6 +63,60 @@ pub struct AsyncToken<T> {
     serial_atomic: AtomicI64,
     /// A queue of requests with their timestamps.
     request_queue: VecDeque<(i64, Instant)>,
+    /// Whether pending calls are kept when the client disconnects and sent again on resume.
+    replay_pending_calls: bool,
+    /// The encoded calls waiting for a result, kept when `replay_pending_calls` is enabled.
+    pending_calls: BTreeMap<i64, Vec<u8>>,
+    /// The verified TLS certificate chain presented by the peer, leaf first.
+    peer_certificates: Vec<PeerCertificate>,
+    /// The identity mapped from the peer certificate.
+    identity: Option<String>,
+    /// The original source address reported by the PROXY protocol header.
+    source_addr: Option<SocketAddr>,
+    /// Wakes the read loop of the current connection when it is kicked.
+    kick_notify: Option<Arc<Notify>>,
+    /// The application state persisted with the session.
+    state: Vec<u8>,
+    /// When the session expires, `None` while a client is connected.
+    expires_at: Option<SystemTime>,
+    /// Pushes buffered while the client is disconnected.
+    outbox: Outbox,
+    /// Application values keyed by type, living as long as the token.
+    extensions: Extensions,
+    /// When the token was created.
+    create_time: SystemTime,
+    /// When a client last connected to the session.
+    connect_time: Option<SystemTime>,
+    /// When the last frame was received, in milliseconds since the Unix epoch.
+    last_activity: AtomicU64,
+}
+
+/// A snapshot of a session for administration.
+#[derive(Clone, Debug)]
+pub struct SessionInfo {
+    /// The session ID.
+    pub session_id: i64,
+    /// The address of the remote client, `None` if not connected.
+    pub addr: Option<SocketAddr>,
+    /// The identity mapped from the peer certificate.
+    pub identity: Option<String>,
+    /// Whether a client is connected.
+    pub connected: bool,
+    /// When the session was created.
+    pub create_time: SystemTime,
+    /// When a client last connected to the session.
+    pub connect_time: Option<SystemTime>,
+    /// When the last frame was received from the client.
+    pub last_activity: SystemTime,
+}
+
+/// Gets the current time in milliseconds since the Unix epoch.
+#[inline]
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or(0)
 }
 
 unsafe impl<T: IController> Send for AsyncToken<T> {}
@@ -37,16 +126,37 @@ unsafe impl<T: IController> Sync for AsyncToken<T> {}
 pub type NetxToken<T> = Arc<Actor<AsyncToken<T>>>;
 
 impl<T: IController> AsyncToken<T> {
-    /// Creates a new `AsyncToken` with the given session ID and manager.
-    pub(crate) fn new(session_id: i64, manager: Weak<dyn IAsyncTokenManager<T>>) -> AsyncToken<T> {
+    /// Creates a new `AsyncToken` with the given session ID, resume token, state, outbox and manager.
+    pub(crate) fn new(
+        session_id: i64,
+        resume_token: [u8; RESUME_TOKEN_LEN],
+        state: Vec<u8>,
+        outbox: Outbox,
+        replay_pending_calls: bool,
+        manager: Weak<dyn IAsyncTokenManager<T>>,
+    ) -> AsyncToken<T> {
         AsyncToken {
             session_id,
+            resume_token,
             controller: None,
             peer: None,
             manager,
             result_dict: Default::default(),
             serial_atomic: AtomicI64::new(1),
             request_queue: Default::default(),
+            replay_pending_calls,
+            pending_calls: BTreeMap::new(),
+            peer_certificates: Vec::new(),
+            identity: None,
+            source_addr: None,
+            kick_notify: None,
+            state,
+            expires_at: None,
+            outbox,
+            extensions: Extensions::default(),
+            create_time: SystemTime::now(),
+            connect_time: None,
+            last_activity: AtomicU64::new(unix_millis()),
         }
     }
 }
@@ -84,6 +194,61 @@ impl<T: IController> AsyncToken<T> {
         anyhow::bail!("controller is none")
     }
 
+    /// Executes a reserved topic command sent by `INetXClient::subscribe` or `unsubscribe`.
+    async fn execute_topic_command(
+        &self,
+        cmd: i32,
+        mut dr: DataOwnedReader,
+    ) -> anyhow::Result<RetResult> {
+        anyhow::ensure!(dr.read_fixed::<u32>()? == 1, "args len error");
+        let topic = dr.pack_deserialize::<String>()?;
+        let manager = self
+            .manager
+            .upgrade()
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        if cmd == SpecialFunctionTag::Subscribe as i32 {
+            anyhow::ensure!(
+                manager
+                    .client_subscribe(self.session_id, topic.clone())
+                    .await,
+                "subscribe {} not allowed",
+                topic
+            );
+        } else {
+            manager.unsubscribe(self.session_id, &topic).await;
+        }
+        Ok(RetResult::success())
+    }
+
+    /// Gets the secret needed to resume the session.
+    #[inline]
+    pub(crate) fn get_resume_token(&self) -> &[u8] {
+        &self.resume_token
+    }
+
+    /// Records that a frame was received from the client.
+    #[inline]
+    pub(crate) fn touch(&self) {
+        self.last_activity.store(unix_millis(), Ordering::Relaxed)
+    }
+
+    /// Gets the record persisted by the `SessionStore`.
+    #[inline]
+    pub(crate) fn get_session_record(&self) -> SessionRecord {
+        SessionRecord {
+            session_id: self.session_id,
+            resume_token: self.resume_token.to_vec(),
+            expires_at: self.expires_at,
+            state: self.state.clone(),
+        }
+    }
+
+    /// Checks the secret presented by a client in constant time.
+    #[inline]
+    pub(crate) fn check_resume_token(&self, resume_token: &[u8]) -> bool {
+        resume_token_eq(&self.resume_token, resume_token)
+    }
+
     /// Generates a new serial number.
     #[inline]
     pub(crate) fn new_serial(&self) -> i64 {
@@ -93,6 +258,7 @@ impl<T: IController> AsyncToken<T> {
     /// Sets an error for the given serial number.
     #[inline]
     pub fn set_error(&mut self, serial: i64, err: crate::error::Error) -> crate::error::Result<()> {
+        self.pending_calls.remove(&serial);
         if let Some(tx) = self.result_dict.remove(&serial) {
             Ok(tx
                 .send(Err(err))
@@ -102,6 +268,25 @@ impl<T: IController> AsyncToken<T> {
         }
     }
 
+    /// Handles the loss of the current connection.
+    ///
+    /// Pending calls can no longer be answered, they fail at once with `TokenDisconnect`
+    /// unless `replay_pending_calls` keeps them to be sent again on resume.
+    fn peer_closed(&mut self) {
+        if self.replay_pending_calls {
+            return;
+        }
+        for (serial, tx) in self.result_dict.drain() {
+            if tx
+                .send(Err(crate::error::Error::TokenDisconnect(self.session_id)))
+                .is_err()
+            {
+                log::debug!("serial:{} is close", serial);
+            }
+        }
+        self.request_queue.clear();
+    }
+
     /// Checks for request timeouts and sets errors for timed-out requests.
     #[inline]
     pub fn check_request_timeout(&mut self, request_out_time: u32) {
@@ -131,15 +316,70 @@ pub(crate) trait IAsyncTokenInner {
     /// * `controller` - An `Arc` reference to the controller.
     async fn set_controller(&self, controller: Arc<Self::Controller>);
 
-    /// Clears all function mappings of the controller.
+    /// Clears all function mappings of the controller and the extensions.
     async fn clear_controller_fun_maps(&self);
 
     /// Sets the network peer for the asynchronous token.
     ///
+    /// When a peer is set, the messages buffered in the outbox are sent to it first, in order.
+    /// When the peer is removed, the pending calls fail, see `ServerOption::replay_pending_calls`.
+    ///
     /// # Arguments
     ///
     /// * `peer` - An optional `Arc` reference to the network peer.
-    async fn set_peer(&self, peer: Option<Arc<NetPeer>>);
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<Notify>` - The notify woken when this connection is kicked, each connection has its own.
+    async fn set_peer(&self, peer: Option<Arc<NetPeer>>) -> Arc<Notify>;
+
+    /// Removes the network peer if it is still the current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer` - The network peer of the closed connection.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - `true` if the peer was removed, `false` if it was replaced or kicked.
+    async fn remove_peer(&self, peer: &Arc<NetPeer>) -> bool;
+
+    /// Closes the current connection, waking its read loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `take_peer` - `true` when a new connection takes over the session, so the
+    ///   kicked connection skips the disconnect handling, `false` to disconnect as usual.
+    async fn kick_peer(&self, take_peer: bool);
+
+    /// Sets when the session expires.
+    ///
+    /// # Arguments
+    ///
+    /// * `expires_at` - The expiry time, `None` while a client is connected.
+    async fn set_expires_at(&self, expires_at: Option<SystemTime>);
+
+    /// Gets the record persisted by the `SessionStore`.
+    async fn get_session_record(&self) -> SessionRecord;
+
+    /// Sets the verified peer certificate chain and the identity mapped from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `certificates` - The peer certificate chain, leaf first.
+    /// * `identity` - The identity mapped from the certificate.
+    async fn set_peer_certificates(
+        &self,
+        certificates: Vec<PeerCertificate>,
+        identity: Option<String>,
+    );
+
+    /// Sets the original source address reported by the PROXY protocol header.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_addr` - The source address, `None` if the connection was not proxied.
+    async fn set_source_addr(&self, source_addr: Option<SocketAddr>);
 
     /// Calls a special function on the controller, such as disconnect or connect.
     ///
@@ -198,14 +438,140 @@ impl<T: IController + 'static> IAsyncTokenInner for Actor<AsyncToken<T>> {
     async fn clear_controller_fun_maps(&self) {
         self.inner_call(|inner| async move {
             inner.get_mut().controller = None;
+            inner.get_mut().extensions.clear();
         })
         .await
     }
 
     #[inline]
-    async fn set_peer(&self, peer: Option<Arc<NetPeer>>) {
+    async fn set_peer(&self, peer: Option<Arc<NetPeer>>) -> Arc<Notify> {
         self.inner_call(|inner| async move {
-            inner.get_mut().peer = peer;
+            let token = inner.get_mut();
+            let reconnect = token.peer.is_none();
+            if token.peer.is_some() && peer.is_none() {
+                token.peer_closed();
+            }
+            let kick_notify = Arc::new(Notify::new());
+            token.kick_notify = peer.as_ref().map(|_| kick_notify.clone());
+            token.peer = peer;
+            if let Some(ref peer) = token.peer {
+                token.connect_time = Some(SystemTime::now());
+                token.touch();
+                if reconnect {
+                    for (serial, buff) in token.pending_calls.iter() {
+                        if let Err(err) = peer.send_all(buff.clone()).await {
+                            log::warn!(
+                                "session id:{} replay serial:{} error:{}",
+                                token.session_id,
+                                serial,
+                                err
+                            );
+                            break;
+                        }
+                    }
+                }
+                let mut queue = token.outbox.take();
+                let mut flushed = 0;
+                while let Some(buff) = queue.pop_front() {
+                    if let Err(err) = peer.send_all(buff.clone()).await {
+                        log::warn!("session id:{} flush outbox error:{}", token.session_id, err);
+                        queue.push_front(buff);
+                        break;
+                    }
+                    flushed += 1;
+                }
+                token.outbox.flushed(flushed);
+                token.outbox.restore(queue);
+            }
+            kick_notify
+        })
+        .await
+    }
+
+    #[inline]
+    async fn remove_peer(&self, peer: &Arc<NetPeer>) -> bool {
+        let peer = peer.clone();
+        self.inner_call(|inner| async move {
+            let token = inner.get_mut();
+            match token.peer {
+                Some(ref current) if Arc::ptr_eq(current, &peer) => {
+                    token.peer = None;
+                    token.kick_notify = None;
+                    token.peer_closed();
+                    true
+                }
+                _ => false,
+            }
+        })
+        .await
+    }
+
+    #[inline]
+    async fn set_expires_at(&self, expires_at: Option<SystemTime>) {
+        self.inner_call(|inner| async move {
+            inner.get_mut().expires_at = expires_at;
+        })
+        .await
+    }
+
+    #[inline]
+    async fn get_session_record(&self) -> SessionRecord {
+        self.inner_call(|inner| async move { inner.get().get_session_record() })
+            .await
+    }
+
+    #[inline]
+    async fn kick_peer(&self, take_peer: bool) {
+        let peer = self
+            .inner_call(|inner| async move {
+                // only the current connection is kicked, an older one kept by
+                // `DuplicateSessionPolicy::AllowBoth` has its own notify
+                if let Some(ref kick_notify) = inner.get().kick_notify {
+                    kick_notify.notify_one();
+                }
+                if take_peer {
+                    inner.get_mut().kick_notify = None;
+                    let peer = inner.get_mut().peer.take();
+                    inner.get_mut().peer_closed();
+                    peer
+                } else {
+                    inner.get().peer.clone()
+                }
+            })
+            .await;
+        if let Some(peer) = peer {
+            log::info!(
+                "session id:{} addr:{} kicked",
+                self.get_session_id(),
+                peer.addr()
+            );
+            if let Err(err) = peer.disconnect().await {
+                log::debug!(
+                    "session id:{} disconnect error:{}",
+                    self.get_session_id(),
+                    err
+                );
+            }
+        }
+    }
+
+    #[inline]
+    async fn set_peer_certificates(
+        &self,
+        certificates: Vec<PeerCertificate>,
+        identity: Option<String>,
+    ) {
+        self.inner_call(|inner| async move {
+            inner.get_mut().peer_certificates = certificates;
+            inner.get_mut().identity = identity;
+        })
+        .await
+    }
+
+    #[inline]
+    async fn set_source_addr(&self, source_addr: Option<SocketAddr>) {
+        self.inner_call(|inner| async move {
+            inner.get_mut().source_addr = source_addr;
         })
         .await
     }
@@ -218,7 +584,15 @@ impl<T: IController + 'static> IAsyncTokenInner for Actor<AsyncToken<T>> {
     #[inline]
     async fn execute_controller(&self, tt: u8, cmd: i32, dr: DataOwnedReader) -> RetResult {
         unsafe {
-            match self.deref_inner().execute_controller(tt, cmd, dr).await {
+            let inner = self.deref_inner();
+            let res = if cmd == SpecialFunctionTag::Subscribe as i32
+                || cmd == SpecialFunctionTag::Unsubscribe as i32
+            {
+                inner.execute_topic_command(cmd, dr).await
+            } else {
+                inner.execute_controller(tt, cmd, dr).await
+            };
+            match res {
                 Ok(res) => res,
                 Err(err) => {
                     log::error!(
@@ -244,7 +618,11 @@ impl<T: IController + 'static> IAsyncTokenInner for Actor<AsyncToken<T>> {
     #[inline]
     async fn set_result(&self, serial: i64, dr: DataOwnedReader) -> anyhow::Result<()> {
         let have_tx: Option<Sender<crate::error::Result<DataOwnedReader>>> = self
-            .inner_call(|inner| async move { inner.get_mut().result_dict.remove(&serial) })
+            .inner_call(|inner| async move {
+                let token = inner.get_mut();
+                token.pending_calls.remove(&serial);
+                token.result_dict.remove(&serial)
+            })
             .await;
 
         if let Some(tx) = have_tx {
@@ -305,6 +683,108 @@ pub trait IAsyncToken {
     /// * `impl std::future::Future<Output = Option<Arc<NetPeer>>>` - A future that resolves to an optional `Arc` reference to the network peer.
     fn get_peer(&self) -> impl std::future::Future<Output = Option<Arc<NetPeer>>>;
 
+    /// Gets the verified TLS certificate chain presented by the peer, leaf first.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Vec<PeerCertificate>>` - A future that resolves to the certificate chain, empty if the peer sent none.
+    fn get_peer_certificates(&self) -> impl std::future::Future<Output = Vec<PeerCertificate>>;
+
+    /// Gets the identity mapped from the peer certificate.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Option<String>>` - A future that resolves to the identity, if any.
+    fn get_identity(&self) -> impl std::future::Future<Output = Option<String>>;
+
+    /// Gets the address of the remote client.
+    ///
+    /// When `ServerOption::proxy_protocol` is enabled this is the original source
+    /// address from the PROXY protocol header, otherwise the peer address.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Option<SocketAddr>>` - A future that resolves to the address, `None` if not connected.
+    fn get_remote_addr(&self) -> impl std::future::Future<Output = Option<SocketAddr>>;
+
+    /// Gets a snapshot of the session for administration.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = SessionInfo>` - A future that resolves to the session info.
+    fn get_session_info(&self) -> impl std::future::Future<Output = SessionInfo>;
+
+    /// Gets the application state persisted with the session.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Vec<u8>>` - A future that resolves to the state, empty if never set.
+    fn get_session_state(&self) -> impl std::future::Future<Output = Vec<u8>>;
+
+    /// Sets the application state persisted with the session by the `SessionStore`.
+    ///
+    /// Keep it small, it is saved every time the session connects or disconnects.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The state, serialized by the application.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<()>>` - A future that resolves to a `Result`.
+    fn set_session_state(
+        &self,
+        state: Vec<u8>,
+    ) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Inserts a typed value that lives as long as the session.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value, replacing the value of the same type.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Option<E>>` - A future that resolves to the replaced value, if any.
+    fn insert_extension<E: Any + Send + Sync>(
+        &self,
+        value: E,
+    ) -> impl std::future::Future<Output = Option<E>>;
+
+    /// Gets a clone of the typed value inserted with `insert_extension`.
+    ///
+    /// Insert an `Arc` to share a value instead of cloning it.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Option<E>>` - A future that resolves to the value, or `None` if not inserted.
+    fn get_extension<E: Any + Send + Sync + Clone>(
+        &self,
+    ) -> impl std::future::Future<Output = Option<E>>;
+
+    /// Updates the typed value inserted with `insert_extension` in place.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Called with the value, or `None` if not inserted.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = R>` - A future that resolves to the result of `f`.
+    fn update_extension<E: Any + Send + Sync, R: Send + 'static>(
+        &self,
+        f: impl FnOnce(Option<&mut E>) -> R + Send + 'static,
+    ) -> impl std::future::Future<Output = R>;
+
+    /// Removes the typed value inserted with `insert_extension`.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Option<E>>` - A future that resolves to the removed value, if any.
+    fn remove_extension<E: Any + Send + Sync>(
+        &self,
+    ) -> impl std::future::Future<Output = Option<E>>;
+
     /// Sends a buffer.
     ///
     /// # Arguments
@@ -339,6 +819,122 @@ pub trait IAsyncToken {
         &self,
     ) -> impl std::future::Future<Output = crate::error::Result<Vec<NetxToken<Self::Controller>>>>;
 
+    /// Binds an application key, like a user id or device id, to this token.
+    ///
+    /// The binding is removed when the token is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<()>>` - A future that resolves to a `Result`.
+    fn bind_key(&self, key: &str) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Unbinds an application key from this token.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<()>>` - A future that resolves to a `Result`.
+    fn unbind_key(&self, key: &str) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Gets the network tokens bound to an application key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<Vec<NetxToken<Self::Controller>>>>` - A future that resolves to the bound tokens.
+    fn get_tokens_by_key(
+        &self,
+        key: &str,
+    ) -> impl std::future::Future<Output = crate::error::Result<Vec<NetxToken<Self::Controller>>>>;
+
+    /// Adds this token to a named group.
+    ///
+    /// The token leaves the group when it is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<()>>` - A future that resolves to a `Result`.
+    fn join_group(
+        &self,
+        group: &str,
+    ) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Removes this token from a group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<()>>` - A future that resolves to a `Result`.
+    fn leave_group(
+        &self,
+        group: &str,
+    ) -> impl std::future::Future<Output = crate::error::Result<()>>;
+
+    /// Gets the member tokens of a group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<Vec<NetxToken<Self::Controller>>>>` - A future that resolves to the member tokens.
+    fn get_group_members(
+        &self,
+        group: &str,
+    ) -> impl std::future::Future<Output = crate::error::Result<Vec<NetxToken<Self::Controller>>>>;
+
+    /// Sends an encoded run call to the members of a group, see `ITokenManager::broadcast`.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    /// * `buff` - The encoded run call.
+    /// * `except_self` - Whether to skip this token.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<Vec<(i64, Error)>>>` - A future that resolves to the session ID and error of each member the call could not be sent to.
+    fn broadcast(
+        &self,
+        group: &str,
+        buff: Data,
+        except_self: bool,
+    ) -> impl std::future::Future<Output = crate::error::Result<Vec<(i64, crate::error::Error)>>>;
+
+    /// Sends an encoded run call to the subscribers of a topic, see `ITokenManager::publish`.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `buff` - The encoded run call.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = Result<Vec<(i64, Error)>>>` - A future that resolves to the session ID and error of each subscriber the call could not be sent to.
+    fn publish(
+        &self,
+        topic: &str,
+        buff: Data,
+    ) -> impl std::future::Future<Output = crate::error::Result<Vec<(i64, crate::error::Error)>>>;
+
     /// Calls a function with the given serial and buffer.
     ///
     /// # Arguments
@@ -366,6 +962,13 @@ pub trait IAsyncToken {
     /// * `impl std::future::Future<Output = Result<()>>` - A future that resolves to a `Result`.
     fn run(&self, buff: Data) -> impl std::future::Future<Output = crate::error::Result<()>>;
 
+    /// Gets the counters of the outbox buffering pushes while the client is disconnected.
+    ///
+    /// # Returns
+    ///
+    /// * `impl std::future::Future<Output = OutboxStats>` - A future that resolves to the outbox counters.
+    fn get_outbox_stats(&self) -> impl std::future::Future<Output = OutboxStats>;
+
     /// Checks if the connection is disconnected.
     ///
     /// # Returns
@@ -394,6 +997,96 @@ impl<T: IController + 'static> IAsyncToken for Actor<AsyncToken<T>> {
             .await
     }
 
+    #[inline]
+    async fn get_peer_certificates(&self) -> Vec<PeerCertificate> {
+        self.inner_call(|inner| async move { inner.get().peer_certificates.clone() })
+            .await
+    }
+
+    #[inline]
+    async fn get_identity(&self) -> Option<String> {
+        self.inner_call(|inner| async move { inner.get().identity.clone() })
+            .await
+    }
+
+    #[inline]
+    async fn get_remote_addr(&self) -> Option<SocketAddr> {
+        self.inner_call(|inner| async move {
+            let token = inner.get();
+            token
+                .source_addr
+                .or_else(|| token.peer.as_ref().map(|peer| peer.addr()))
+        })
+        .await
+    }
+
+    #[inline]
+    async fn get_session_info(&self) -> SessionInfo {
+        self.inner_call(|inner| async move {
+            let token = inner.get();
+            SessionInfo {
+                session_id: token.session_id,
+                addr: token
+                    .peer
+                    .as_ref()
+                    .map(|peer| token.source_addr.unwrap_or_else(|| peer.addr())),
+                identity: token.identity.clone(),
+                connected: token.peer.is_some(),
+                create_time: token.create_time,
+                connect_time: token.connect_time,
+                last_activity: UNIX_EPOCH
+                    + Duration::from_millis(token.last_activity.load(Ordering::Relaxed)),
+            }
+        })
+        .await
+    }
+
+    #[inline]
+    async fn get_session_state(&self) -> Vec<u8> {
+        self.inner_call(|inner| async move { inner.get().state.clone() })
+            .await
+    }
+
+    #[inline]
+    async fn set_session_state(&self, state: Vec<u8>) -> crate::error::Result<()> {
+        let manager = self
+            .inner_call(|inner| async move {
+                inner.get_mut().state = state;
+                inner.get().manager.upgrade()
+            })
+            .await
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        manager.save_session(self.get_session_id()).await;
+        Ok(())
+    }
+
+    #[inline]
+    async fn insert_extension<E: Any + Send + Sync>(&self, value: E) -> Option<E> {
+        self.inner_call(|inner| async move { inner.get_mut().extensions.insert(value) })
+            .await
+    }
+
+    #[inline]
+    async fn get_extension<E: Any + Send + Sync + Clone>(&self) -> Option<E> {
+        self.inner_call(|inner| async move { inner.get().extensions.get::<E>().cloned() })
+            .await
+    }
+
+    #[inline]
+    async fn update_extension<E: Any + Send + Sync, R: Send + 'static>(
+        &self,
+        f: impl FnOnce(Option<&mut E>) -> R + Send + 'static,
+    ) -> R {
+        self.inner_call(|inner| async move { f(inner.get_mut().extensions.get_mut::<E>()) })
+            .await
+    }
+
+    #[inline]
+    async fn remove_extension<E: Any + Send + Sync>(&self) -> Option<E> {
+        self.inner_call(|inner| async move { inner.get_mut().extensions.remove::<E>() })
+            .await
+    }
+
     #[inline]
     async fn send(&self, buff: Vec<u8>) -> crate::error::Result<()> {
         unsafe {
@@ -431,8 +1124,87 @@ impl<T: IController + 'static> IAsyncToken for Actor<AsyncToken<T>> {
         .await
     }
 
+    #[inline]
+    async fn bind_key(&self, key: &str) -> crate::error::Result<()> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        manager.bind_key(self.get_session_id(), key).await;
+        Ok(())
+    }
+
+    #[inline]
+    async fn unbind_key(&self, key: &str) -> crate::error::Result<()> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        manager.unbind_key(self.get_session_id(), key).await;
+        Ok(())
+    }
+
+    #[inline]
+    async fn get_tokens_by_key(&self, key: &str) -> crate::error::Result<Vec<NetxToken<T>>> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        Ok(manager.get_tokens_by_key(key).await)
+    }
+
+    #[inline]
+    async fn join_group(&self, group: &str) -> crate::error::Result<()> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        manager.join_group(self.get_session_id(), group).await;
+        Ok(())
+    }
+
+    #[inline]
+    async fn leave_group(&self, group: &str) -> crate::error::Result<()> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        manager.leave_group(self.get_session_id(), group).await;
+        Ok(())
+    }
+
+    #[inline]
+    async fn get_group_members(&self, group: &str) -> crate::error::Result<Vec<NetxToken<T>>> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        Ok(manager.get_group_members(group).await)
+    }
+
+    #[inline]
+    async fn broadcast(
+        &self,
+        group: &str,
+        buff: Data,
+        except_self: bool,
+    ) -> crate::error::Result<Vec<(i64, crate::error::Error)>> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        let except = if except_self {
+            Some(self.get_session_id())
+        } else {
+            None
+        };
+        Ok(manager.broadcast(group, buff, except).await)
+    }
+
+    #[inline]
+    async fn publish(
+        &self,
+        topic: &str,
+        buff: Data,
+    ) -> crate::error::Result<Vec<(i64, crate::error::Error)>> {
+        let manager = unsafe { self.deref_inner().manager.upgrade() }
+            .ok_or(crate::error::Error::ManagerUpgradeFail)?;
+        Ok(manager.publish(topic, buff).await)
+    }
+
     #[inline]
     async fn call(&self, serial: i64, buff: Data) -> crate::error::Result<RetResult> {
+        let replay = if unsafe { self.deref_inner().replay_pending_calls } {
+            Some(buff.to_vec())
+        } else {
+            None
+        };
         let (peer, rx): (
             Arc<NetPeer>,
             Receiver<crate::error::Result<DataOwnedReader>>,
@@ -452,6 +1224,9 @@ impl<T: IController + 'static> IAsyncToken for Actor<AsyncToken<T>> {
                             .request_queue
                             .push_front((serial, Instant::now()));
                     }
+                    if let Some(replay) = replay {
+                        inner.get_mut().pending_calls.insert(serial, replay);
+                    }
                     Ok((peer, rx))
                 } else {
                     Err(crate::error::Error::TokenDisconnect(inner.get().session_id))
@@ -469,17 +1244,30 @@ impl<T: IController + 'static> IAsyncToken for Actor<AsyncToken<T>> {
     async fn run(&self, buff: Data) -> crate::error::Result<()> {
         let peer = self
             .inner_call(|inner| async move {
-                if let Some(peer) = inner.get().peer.clone() {
-                    Ok(peer)
+                let token = inner.get_mut();
+                if let Some(peer) = token.peer.clone() {
+                    Ok(Some((peer, buff)))
+                } else if !token.outbox.is_enabled() {
+                    Err(crate::error::Error::TokenDisconnect(token.session_id))
+                } else if token.outbox.push(buff.into_inner()) {
+                    Ok(None)
                 } else {
-                    Err(crate::error::Error::TokenDisconnect(inner.get().session_id))
+                    Err(crate::error::Error::OutboxFull(token.session_id))
                 }
             })
             .await?;
-        peer.send_all(buff.into_inner()).await?;
+        if let Some((peer, buff)) = peer {
+            peer.send_all(buff.into_inner()).await?;
+        }
         Ok(())
     }
 
+    #[inline]
+    async fn get_outbox_stats(&self) -> OutboxStats {
+        self.inner_call(|inner| async move { inner.get().outbox.get_stats() })
+            .await
+    }
+
     #[inline]
     async fn is_disconnect(&self) -> bool {
         self.inner_call(|inner| async move {
@@ -574,6 +1362,86 @@ macro_rules! call_peer {
                  log::warn!{"run {} is error:{}",$cmd,err}
             }
     });
+    (@broadcast $peer:expr=>$group:expr,$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call_peer!(@count $($args),*) as i32;
+            let serial=$peer.new_serial();
+            data.write_fixed(0u32);
+            data.write_fixed(2400u32);
+            data.write_fixed(0u8);
+            data.write_fixed($cmd);
+            data.write_fixed(serial);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            let len=data.len();
+            (&mut data[0..4]).put_u32_le(len as u32);
+            $peer.broadcast($group,data,true).await?
+    });
+    (@publish $server:expr=>$topic:expr,$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call_peer!(@count $($args),*) as i32;
+            data.write_fixed(0u32);
+            data.write_fixed(2400u32);
+            data.write_fixed(0u8);
+            data.write_fixed($cmd);
+            data.write_fixed(0i64);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            let len=data.len();
+            (&mut data[0..4]).put_u32_le(len as u32);
+            $server.publish($topic,data).await
+    });
+    (@call_all $server:expr=>$tokens:expr,$timeout:expr,$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call_peer!(@count $($args),*) as i32;
+            data.write_fixed(0u32);
+            data.write_fixed(2400u32);
+            data.write_fixed(2u8);
+            data.write_fixed($cmd);
+            data.write_fixed(0i64);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            let len=data.len();
+            (&mut data[0..4]).put_u32_le(len as u32);
+            $server.call_all($tokens,data,$timeout).await
+                .into_iter()
+                .map(|(session_id,result)|(session_id,result.and_then(|ret|ret.check()?.deserialize())))
+                .collect::<Vec<_>>()
+    });
+    (@cluster $cluster:expr=>$session_id:expr,$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call_peer!(@count $($args),*) as i32;
+            data.write_fixed(0u32);
+            data.write_fixed(2400u32);
+            data.write_fixed(2u8);
+            data.write_fixed($cmd);
+            data.write_fixed(0i64);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            let len=data.len();
+            (&mut data[0..4]).put_u32_le(len as u32);
+            let mut ret= $cluster.call($session_id,data).await?.check()?;
+            ret.deserialize()?
+    });
+    (@cluster_run $cluster:expr=>$session_id:expr,$cmd:expr;$($args:expr), *$(,)*) => ({
+            use data_rw::Data;
+            let mut data=Data::with_capacity(128);
+            let args_count=call_peer!(@count $($args),*) as i32;
+            data.write_fixed(0u32);
+            data.write_fixed(2400u32);
+            data.write_fixed(0u8);
+            data.write_fixed($cmd);
+            data.write_fixed(0i64);
+            data.write_fixed(args_count);
+            $(data.pack_serialize($args)?;)*
+            let len=data.len();
+            (&mut data[0..4]).put_u32_le(len as u32);
+            $cluster.run($session_id,data).await?;
+    });
     (@checkrun $peer:expr=>$cmd:expr;$($args:expr), *$(,)*) => ({
             use data_rw::Data;
             let mut data=Data::with_capacity(128);
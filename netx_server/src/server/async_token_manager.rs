@@ -1,19 +1,44 @@
 use crate::async_token::{IAsyncToken, IAsyncTokenInner};
-use crate::controller::ICreateController;
+use crate::controller::{IController, ICreateController};
 use crate::impl_server::SpecialFunctionTag;
-use crate::server::async_token::{AsyncToken, NetxToken};
+use crate::server::async_token::{
+    resume_token_eq, AsyncToken, NetxToken, SessionInfo, RESUME_TOKEN_LEN,
+};
+use crate::server::bindings::Bindings;
+use crate::server::outbox::Outbox;
+use crate::server::session_event::{SessionEvent, SessionEventKind, SESSION_EVENT_CAPACITY};
+use crate::server::session_store::{SessionRecord, SessionStore, SessionStoreQueue};
+use crate::{OutboxOverflow, RetResult};
 use aqueue::Actor;
+use data_rw::Data;
 use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::net::SocketAddr;
 use std::sync::{Arc, Weak};
+use std::time::SystemTime;
+use tokio::sync::broadcast;
 use tokio::time::{sleep, Duration, Instant};
 
 /// Manages asynchronous tokens, including their creation, timeout checks, and disconnection handling.
 pub struct AsyncTokenManager<T: ICreateController + 'static> {
     impl_controller: T,
     dict: HashMap<i64, NetxToken<T::Controller>>,
+    /// The application keys bound to sessions.
+    keys: Bindings,
+    /// The groups sessions are members of.
+    groups: Bindings,
+    /// The topics sessions are subscribed to.
+    topics: Bindings,
     request_out_time: u32,
     session_save_time: u32,
+    outbox_capacity: usize,
+    outbox_overflow: OutboxOverflow,
+    replay_pending_calls: bool,
+    /// The sessions to remove when they expire and the time they expire, the latest first.
     request_disconnect_clear_queue: VecDeque<(i64, Instant)>,
+    /// Persists sessions across restarts, `None` keeps them in memory only.
+    session_store: Option<SessionStoreQueue>,
+    events: broadcast::Sender<SessionEvent>,
 }
 
 unsafe impl<T: ICreateController + 'static> Send for AsyncTokenManager<T> {}
@@ -30,6 +55,9 @@ impl<T: ICreateController + 'static> AsyncTokenManager<T> {
     /// * `impl_controller` - The controller implementation.
     /// * `request_out_time` - The timeout duration for requests.
     /// * `session_save_time` - The duration to save sessions.
+    /// * `outbox_capacity` - The maximum number of pushes buffered per disconnected session.
+    /// * `outbox_overflow` - What happens when an outbox is full.
+    /// * `replay_pending_calls` - Whether pending calls are sent again when a session is resumed.
     ///
     /// # Returns
     ///
@@ -39,13 +67,24 @@ impl<T: ICreateController + 'static> AsyncTokenManager<T> {
         impl_controller: T,
         request_out_time: u32,
         session_save_time: u32,
+        outbox_capacity: usize,
+        outbox_overflow: OutboxOverflow,
+        replay_pending_calls: bool,
     ) -> TokenManager<T> {
         let ptr = Arc::new(Actor::new(AsyncTokenManager {
             impl_controller,
             dict: HashMap::new(),
+            keys: Bindings::default(),
+            groups: Bindings::default(),
+            topics: Bindings::default(),
             request_out_time,
             session_save_time,
+            outbox_capacity,
+            outbox_overflow,
+            replay_pending_calls,
             request_disconnect_clear_queue: Default::default(),
+            session_store: None,
+            events: broadcast::channel(SESSION_EVENT_CAPACITY).0,
         }));
 
         Self::start_check(Arc::downgrade(&ptr));
@@ -81,25 +120,20 @@ impl<T: ICreateController + 'static> AsyncTokenManager<T> {
     #[inline]
     async fn check_tokens_disconnect_timeout(&mut self) {
         while let Some(item) = self.request_disconnect_clear_queue.pop_back() {
-            if item.1.elapsed().as_millis() as u32 >= self.session_save_time {
+            if item.1 <= Instant::now() {
                 if let Some(token) = self.dict.get(&item.0) {
                     if token.is_disconnect().await {
-                        if let Some(token) = self.dict.remove(&item.0) {
-                            if let Err(er) = token
-                                .call_special_function(SpecialFunctionTag::Closed as i32)
-                                .await
-                            {
-                                log::error!("call token Closed err:{}", er)
-                            }
-                            token.clear_controller_fun_maps().await;
-                            log::debug!("token {} remove", token.get_session_id());
-                        } else {
+                        if !self.remove_token(item.0).await {
                             log::debug!("remove token {} fail", item.0);
                         }
                     } else {
                         log::debug!("remove token {},but it not disconnect", item.0);
                     }
                 } else {
+                    // a session restored from the store that no client resumed
+                    if let Some(store) = &self.session_store {
+                        store.remove(item.0);
+                    }
                     log::debug!("remove token not found {}", item.0);
                 }
             } else {
@@ -109,14 +143,202 @@ impl<T: ICreateController + 'static> AsyncTokenManager<T> {
         }
     }
 
-    /// Generates a new session ID.
+    /// Removes a token, calling its closed event and removing it from the `SessionStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found and removed.
+    #[inline]
+    async fn remove_token(&mut self, session_id: i64) -> bool {
+        if let Some(token) = self.dict.remove(&session_id) {
+            if let Err(er) = token
+                .call_special_function(SpecialFunctionTag::Closed as i32)
+                .await
+            {
+                log::error!("call token Closed err:{}", er)
+            }
+            token.clear_controller_fun_maps().await;
+            self.keys.unbind_all(session_id);
+            self.groups.unbind_all(session_id);
+            self.topics.unbind_all(session_id);
+            if let Some(store) = &self.session_store {
+                store.remove(session_id);
+            }
+            log::debug!("token {} remove", session_id);
+            self.send_event(session_id, None, SessionEventKind::Expired);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Binds an application key to a token.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `key` - The application key, like a user id.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    #[inline]
+    fn bind_key(&mut self, session_id: i64, key: String) -> bool {
+        if !self.dict.contains_key(&session_id) {
+            return false;
+        }
+        self.keys.bind(session_id, key);
+        true
+    }
+
+    /// Retrieves the tokens bound to an application key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the bound `NetxToken`s.
+    #[inline]
+    pub fn get_tokens_by_key(&self, key: &str) -> Vec<NetxToken<T::Controller>> {
+        self.keys
+            .get_sessions(key)
+            .filter_map(|session_id| self.dict.get(session_id).cloned())
+            .collect()
+    }
+
+    /// Retrieves the application keys bound to a token.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the bound keys.
+    #[inline]
+    pub fn get_keys(&self, session_id: i64) -> Vec<String> {
+        self.keys.get_names(session_id)
+    }
+
+    /// Adds a token to a group.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    #[inline]
+    fn join_group(&mut self, session_id: i64, group: String) -> bool {
+        if !self.dict.contains_key(&session_id) {
+            return false;
+        }
+        self.groups.bind(session_id, group);
+        true
+    }
+
+    /// Retrieves the member tokens of a group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the member `NetxToken`s.
+    #[inline]
+    pub fn get_group_members(&self, group: &str) -> Vec<NetxToken<T::Controller>> {
+        self.groups
+            .get_sessions(group)
+            .filter_map(|session_id| self.dict.get(session_id).cloned())
+            .collect()
+    }
+
+    /// Retrieves the groups a token is a member of.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the group names.
+    #[inline]
+    pub fn get_groups(&self, session_id: i64) -> Vec<String> {
+        self.groups.get_names(session_id)
+    }
+
+    /// Subscribes a token to a topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    #[inline]
+    fn subscribe(&mut self, session_id: i64, topic: String) -> bool {
+        if !self.dict.contains_key(&session_id) {
+            return false;
+        }
+        self.topics.bind(session_id, topic);
+        true
+    }
+
+    /// Retrieves the tokens subscribed to a topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the subscribed `NetxToken`s.
+    #[inline]
+    pub fn get_subscribers(&self, topic: &str) -> Vec<NetxToken<T::Controller>> {
+        self.topics
+            .get_sessions(topic)
+            .filter_map(|session_id| self.dict.get(session_id).cloned())
+            .collect()
+    }
+
+    /// Retrieves the topics a token is subscribed to.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the topic names.
+    #[inline]
+    pub fn get_topics(&self, session_id: i64) -> Vec<String> {
+        self.topics.get_names(session_id)
+    }
+
+    /// Generates a new unguessable session ID from the OS CSPRNG.
     ///
     /// # Returns
     ///
-    /// A new session ID as an `i64`.
+    /// A `Result` containing a new positive session ID that is not in use.
     #[inline]
-    fn make_new_session_id(&mut self) -> i64 {
-        chrono::Local::now().timestamp_nanos_opt().unwrap()
+    fn make_new_session_id(&mut self) -> anyhow::Result<i64> {
+        loop {
+            let session_id = (getrandom::u64()? & i64::MAX as u64) as i64;
+            if session_id != 0 && !self.dict.contains_key(&session_id) {
+                return Ok(session_id);
+            }
+        }
     }
 
     /// Creates a new token.
@@ -133,14 +355,173 @@ impl<T: ICreateController + 'static> AsyncTokenManager<T> {
         &mut self,
         manager: Weak<Actor<AsyncTokenManager<T>>>,
     ) -> anyhow::Result<NetxToken<T::Controller>> {
-        let session_id = self.make_new_session_id();
-        let token = Arc::new(Actor::new(AsyncToken::new(session_id, manager)));
+        let session_id = self.make_new_session_id()?;
+        let mut resume_token = [0u8; RESUME_TOKEN_LEN];
+        getrandom::fill(&mut resume_token)?;
+        let token = self
+            .insert_token(session_id, resume_token, Vec::new(), manager)
+            .await?;
+        self.save_session(&token).await;
+        self.send_event(session_id, None, SessionEventKind::Created);
+        Ok(token)
+    }
+
+    /// Subscribes to the lifecycle events of all sessions.
+    #[inline]
+    pub(crate) fn subscribe_events(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sends a lifecycle event, it is dropped if nobody is subscribed.
+    #[inline]
+    fn send_event(&self, session_id: i64, addr: Option<SocketAddr>, kind: SessionEventKind) {
+        let _ = self.events.send(SessionEvent {
+            session_id,
+            addr,
+            time: SystemTime::now(),
+            kind,
+        });
+    }
+
+    /// Inserts a session loaded from the `SessionStore` whose resume token was checked.
+    ///
+    /// A restored token is removed when its record expires unless a client connects to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The stored session.
+    /// * `manager` - A weak reference to the `Actor` managing the `AsyncTokenManager`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `NetxToken`, the one already in memory if another client restored it first.
+    #[inline]
+    async fn restore_token(
+        &mut self,
+        record: SessionRecord,
+        manager: Weak<Actor<AsyncTokenManager<T>>>,
+    ) -> anyhow::Result<NetxToken<T::Controller>> {
+        let session_id = record.session_id;
+        if let Some(token) = self.dict.get(&session_id) {
+            return Ok(token.clone());
+        }
+        let resume_token = <[u8; RESUME_TOKEN_LEN]>::try_from(record.resume_token.as_slice())?;
+        let token = self
+            .insert_token(session_id, resume_token, record.state, manager)
+            .await?;
+        token.set_expires_at(record.expires_at).await;
+        self.schedule_expire(session_id, record.expires_at);
+        log::debug!("token {} restore from session store", session_id);
+        Ok(token)
+    }
+
+    /// Creates a token with its controller and inserts it.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `resume_token` - The secret needed to resume the session.
+    /// * `state` - The application state of the session.
+    /// * `manager` - A weak reference to the `Actor` managing the `AsyncTokenManager`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the new `NetxToken` or an error.
+    #[inline]
+    async fn insert_token(
+        &mut self,
+        session_id: i64,
+        resume_token: [u8; RESUME_TOKEN_LEN],
+        state: Vec<u8>,
+        manager: Weak<Actor<AsyncTokenManager<T>>>,
+    ) -> anyhow::Result<NetxToken<T::Controller>> {
+        let token = Arc::new(Actor::new(AsyncToken::new(
+            session_id,
+            resume_token,
+            state,
+            Outbox::new(self.outbox_capacity, self.outbox_overflow),
+            self.replay_pending_calls,
+            manager,
+        )));
         let controller = self.impl_controller.create_controller(token.clone())?;
         token.set_controller(controller).await;
         self.dict.insert(session_id, token.clone());
         Ok(token)
     }
 
+    /// Queues saving a token to the `SessionStore`, errors are logged.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The token to save.
+    #[inline]
+    async fn save_session(&self, token: &NetxToken<T::Controller>) {
+        if let Some(store) = &self.session_store {
+            store.save(token.get_session_record().await);
+        }
+    }
+
+    /// Sets the store used to persist sessions and restores the stored sessions.
+    ///
+    /// The restored sessions are removed from the store if no client resumes them before they expire.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_store` - The session store.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the stored sessions were restored.
+    #[inline]
+    async fn set_session_store(
+        &mut self,
+        session_store: Arc<dyn SessionStore>,
+    ) -> anyhow::Result<()> {
+        let kept = session_store
+            .restore(Duration::from_millis(self.session_save_time as u64))
+            .await?;
+        self.session_store = Some(SessionStoreQueue::new(session_store));
+        for (session_id, expires_at) in kept {
+            self.schedule_expire(session_id, expires_at);
+        }
+        Ok(())
+    }
+
+    /// Schedules removing a session when it expires, unless a client is connected to it then.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `expires_at` - When the session expires, `None` for `session_save_time` from now.
+    #[inline]
+    fn schedule_expire(&mut self, session_id: i64, expires_at: Option<SystemTime>) {
+        let now = Instant::now();
+        let deadline = match expires_at {
+            Some(expires_at) => {
+                now + expires_at
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+            }
+            None => now + Duration::from_millis(self.session_save_time as u64),
+        };
+        // the queue is ordered by deadline, the latest at the front
+        let index = self
+            .request_disconnect_clear_queue
+            .partition_point(|(_, at)| *at > deadline);
+        self.request_disconnect_clear_queue
+            .insert(index, (session_id, deadline));
+    }
+
+    /// Retrieves the controller factory.
+    ///
+    /// # Returns
+    ///
+    /// A reference to the `ICreateController` implementation.
+    #[inline]
+    pub(crate) fn get_impl_controller(&self) -> &T {
+        &self.impl_controller
+    }
+
     /// Retrieves a token by its session ID.
     ///
     /// # Arguments
@@ -166,6 +547,16 @@ impl<T: ICreateController + 'static> AsyncTokenManager<T> {
     }
 }
 
+/// The outcome of a client resuming a session.
+pub(crate) enum Resume<T> {
+    /// The resume token matches, the session is in memory or restored from the store.
+    Resumed(NetxToken<T>),
+    /// The session exists but the resume token does not match.
+    Mismatch,
+    /// The session is unknown or expired.
+    NotFound,
+}
+
 /// Trait for creating tokens asynchronously.
 pub(crate) trait IAsyncTokenManagerCreateToken<T> {
     /// Creates a new token.
@@ -178,6 +569,34 @@ pub(crate) trait IAsyncTokenManagerCreateToken<T> {
     ///
     /// A `Result` containing the new `NetxToken` or an error.
     async fn create_token(&self, manager: Weak<Self>) -> anyhow::Result<NetxToken<T>>;
+
+    /// Resumes a session, restoring it from the `SessionStore` if it is not in memory.
+    ///
+    /// A stored session is only restored when the resume token matches,
+    /// the store is read without holding the manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID sent by the client.
+    /// * `resume_token` - The resume token sent by the client.
+    /// * `manager` - A weak reference to the `Actor` managing the `AsyncTokenManager`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing whether the session was resumed.
+    async fn resume_session(
+        &self,
+        session_id: i64,
+        resume_token: &[u8],
+        manager: Weak<Self>,
+    ) -> anyhow::Result<Resume<T>>;
+
+    /// Sets the store used to persist sessions and restores the stored sessions.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_store` - The session store.
+    async fn set_session_store(&self, session_store: Arc<dyn SessionStore>) -> anyhow::Result<()>;
 }
 
 impl<T: ICreateController + 'static> IAsyncTokenManagerCreateToken<T::Controller>
@@ -188,6 +607,57 @@ impl<T: ICreateController + 'static> IAsyncTokenManagerCreateToken<T::Controller
         self.inner_call(|inner| async move { inner.get_mut().create_token(manager).await })
             .await
     }
+
+    async fn resume_session(
+        &self,
+        session_id: i64,
+        resume_token: &[u8],
+        manager: Weak<Self>,
+    ) -> anyhow::Result<Resume<T::Controller>> {
+        let (token, store) = self
+            .inner_call(|inner| async move {
+                let manager = inner.get();
+                (
+                    manager.dict.get(&session_id).cloned(),
+                    manager.session_store.clone(),
+                )
+            })
+            .await;
+        if let Some(token) = token {
+            return Ok(
+                if unsafe { token.deref_inner().check_resume_token(resume_token) } {
+                    Resume::Resumed(token)
+                } else {
+                    Resume::Mismatch
+                },
+            );
+        }
+        let Some(store) = store else {
+            return Ok(Resume::NotFound);
+        };
+        let record = match store.load(session_id).await? {
+            Some(record) => record,
+            None => return Ok(Resume::NotFound),
+        };
+        if record.is_expired() {
+            store.remove(session_id);
+            return Ok(Resume::NotFound);
+        }
+        if !resume_token_eq(&record.resume_token, resume_token) {
+            return Ok(Resume::Mismatch);
+        }
+        self.inner_call(|inner| async move { inner.get_mut().restore_token(record, manager).await })
+            .await
+            .map(Resume::Resumed)
+    }
+
+    #[inline]
+    async fn set_session_store(&self, session_store: Arc<dyn SessionStore>) -> anyhow::Result<()> {
+        self.inner_call(
+            |inner| async move { inner.get_mut().set_session_store(session_store).await },
+        )
+        .await
+    }
 }
 
 /// Trait for managing tokens.
@@ -210,45 +680,534 @@ pub trait ITokenManager<T>: Send + Sync {
     ///
     /// A `Result` containing a `Vec` of all `NetxToken`s or an error.
     async fn get_all_tokens(&self) -> Vec<NetxToken<T>>;
-}
-
-/// Trait for managing tokens asynchronously.
-#[async_trait::async_trait]
-pub(crate) trait IAsyncTokenManager<T>: ITokenManager<T> {
-    /// Checks for tokens that have timed out on requests.
-    async fn check_tokens_request_timeout(&self);
 
-    /// Checks for tokens that have timed out on disconnection.
-    async fn check_tokens_disconnect_timeout(&self);
+    /// Disconnects the client of a session, the session is kept and can be resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the session was found.
+    async fn kick(&self, session_id: i64) -> bool;
 
-    /// Handles peer disconnection.
+    /// Disconnects the client of a session and removes the session immediately,
+    /// as if `session_save_time` had passed.
     ///
     /// # Arguments
     ///
     /// * `session_id` - The session ID of the token.
-    async fn peer_disconnect(&self, session_id: i64);
-}
+    ///
+    /// # Returns
+    ///
+    /// `true` if the session was found.
+    async fn expire(&self, session_id: i64) -> bool;
 
-#[async_trait::async_trait]
-impl<T: ICreateController + 'static> ITokenManager<T::Controller> for Actor<AsyncTokenManager<T>> {
-    #[inline]
-    async fn get_token(&self, session_id: i64) -> Option<NetxToken<T::Controller>> {
-        self.inner_call(|inner| async move { inner.get().get_token(session_id) })
-            .await
-    }
+    /// Lists all sessions with their address, connect time and last activity.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` of `SessionInfo`.
+    async fn get_sessions(&self) -> Vec<SessionInfo>;
 
-    #[inline]
-    async fn get_all_tokens(&self) -> Vec<NetxToken<T::Controller>> {
-        self.inner_call(|inner| async move { inner.get().get_all_tokens() })
-            .await
-    }
-}
+    /// Binds an application key, like a user id or device id, to a token.
+    ///
+    /// A token can have many keys and a key can be bound to many tokens.
+    /// The bindings are removed when the token is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    async fn bind_key(&self, session_id: i64, key: &str) -> bool;
 
-#[async_trait::async_trait]
-impl<T: ICreateController + 'static> IAsyncTokenManager<T::Controller>
-    for Actor<AsyncTokenManager<T>>
-{
-    #[inline]
+    /// Unbinds an application key from a token.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key was bound to the token.
+    async fn unbind_key(&self, session_id: i64, key: &str) -> bool;
+
+    /// Retrieves the tokens bound to an application key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the bound `NetxToken`s, empty if none.
+    async fn get_tokens_by_key(&self, key: &str) -> Vec<NetxToken<T>>;
+
+    /// Retrieves the application keys bound to a token.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the bound keys.
+    async fn get_keys(&self, session_id: i64) -> Vec<String>;
+
+    /// Adds a token to a named group, creating the group if needed.
+    ///
+    /// A token can join many groups. It leaves all of them when it is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    async fn join_group(&self, session_id: i64, group: &str) -> bool;
+
+    /// Removes a token from a group, the group is dropped when it has no members left.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was a member of the group.
+    async fn leave_group(&self, session_id: i64, group: &str) -> bool;
+
+    /// Retrieves the member tokens of a group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the member `NetxToken`s, empty if the group does not exist.
+    async fn get_group_members(&self, group: &str) -> Vec<NetxToken<T>>;
+
+    /// Retrieves the groups a token is a member of.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the group names.
+    async fn get_groups(&self, session_id: i64) -> Vec<String>;
+
+    /// Sends an encoded run call to every member of a group.
+    ///
+    /// The buffer is encoded once, like with `call_peer!(@broadcast ...)`, and sent to each member
+    /// the same way as `IAsyncToken::run`. A member that fails does not stop the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    /// * `buff` - The encoded run call.
+    /// * `except` - A session not to send to, like the sender of a chat message.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and error of each member the call could not be sent to.
+    async fn broadcast(
+        &self,
+        group: &str,
+        buff: Data,
+        except: Option<i64>,
+    ) -> Vec<(i64, crate::error::Error)>;
+
+    /// Subscribes a token to a topic, like a client does with `INetXClient::subscribe`.
+    ///
+    /// The subscriptions are removed when the token is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    async fn subscribe(&self, session_id: i64, topic: &str) -> bool;
+
+    /// Unsubscribes a token from a topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was subscribed to the topic.
+    async fn unsubscribe(&self, session_id: i64, topic: &str) -> bool;
+
+    /// Retrieves the tokens subscribed to a topic.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the subscribed `NetxToken`s, empty if none.
+    async fn get_subscribers(&self, topic: &str) -> Vec<NetxToken<T>>;
+
+    /// Retrieves the topics a token is subscribed to.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the topic names.
+    async fn get_topics(&self, session_id: i64) -> Vec<String>;
+
+    /// Sends an encoded run call to every subscriber of a topic, like `call_peer!(@publish ...)`.
+    ///
+    /// A subscriber that fails does not stop the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `buff` - The encoded run call.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and error of each subscriber the call could not be sent to.
+    async fn publish(&self, topic: &str, buff: Data) -> Vec<(i64, crate::error::Error)>;
+
+    /// Sends an encoded call to many tokens at once and waits for all results, like `call_peer!(@call_all ...)`.
+    ///
+    /// The buffer is encoded once, each token gets it with its own serial. All calls share
+    /// one deadline, a token that has not answered by then gets `Error::SerialTimeOut`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - The tokens to call, like the result of `get_group_members`.
+    /// * `buff` - The encoded call, `call_peer!` encoding with any serial.
+    /// * `timeout` - The time to wait for all results.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and result of each token, in the order of `tokens`.
+    async fn call_all(
+        &self,
+        tokens: Vec<NetxToken<T>>,
+        buff: Data,
+        timeout: Duration,
+    ) -> Vec<(i64, crate::error::Result<RetResult>)>;
+}
+
+/// The offset of the serial in a `call_peer!` encoded call: length, 2400, call type and command.
+const CALL_SERIAL_OFFSET: usize = 4 + 4 + 1 + 4;
+
+/// Replaces the serial of a `call_peer!` encoded call.
+///
+/// # Arguments
+///
+/// * `data` - The encoded call.
+/// * `serial` - The serial of the token the call is sent to.
+///
+/// # Returns
+///
+/// A `Result` indicating whether the buffer is long enough.
+#[inline]
+pub(crate) fn set_call_serial(data: &mut Data, serial: i64) -> anyhow::Result<()> {
+    data.write_fixed_at(CALL_SERIAL_OFFSET, serial)
+}
+
+/// Sends an encoded run call to each token, collecting the errors instead of stopping at the first.
+///
+/// The tokens are sent to concurrently, a slow peer does not hold the others.
+///
+/// # Arguments
+///
+/// * `tokens` - The tokens to send to.
+/// * `buff` - The encoded run call.
+/// * `except` - A session not to send to.
+///
+/// # Returns
+///
+/// A `Vec` containing the session ID and error of each token the call could not be sent to.
+async fn run_all<T: IController + 'static>(
+    tokens: Vec<NetxToken<T>>,
+    buff: Data,
+    except: Option<i64>,
+) -> Vec<(i64, crate::error::Error)> {
+    let buff = buff.into_inner();
+    let runs = tokens
+        .into_iter()
+        .filter(|token| Some(token.get_session_id()) != except)
+        .map(|token| {
+            let session_id = token.get_session_id();
+            let data = Data::from(buff.clone());
+            let run = tokio::spawn(async move { token.run(data).await });
+            (session_id, run)
+        })
+        .collect::<Vec<_>>();
+
+    let mut failed = Vec::new();
+    for (session_id, run) in runs {
+        match run.await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => failed.push((session_id, err)),
+            Err(err) => failed.push((session_id, crate::error::Error::Error(err.into()))),
+        }
+    }
+    failed
+}
+
+/// Trait for managing tokens asynchronously.
+#[async_trait::async_trait]
+pub(crate) trait IAsyncTokenManager<T>: ITokenManager<T> {
+    /// Checks for tokens that have timed out on requests.
+    async fn check_tokens_request_timeout(&self);
+
+    /// Checks for tokens that have timed out on disconnection.
+    async fn check_tokens_disconnect_timeout(&self);
+
+    /// Handles peer disconnection.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `addr` - The address of the remote client.
+    async fn peer_disconnect(&self, session_id: i64, addr: SocketAddr);
+
+    /// Handles peer connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `addr` - The address of the remote client.
+    /// * `resumed` - Whether the client resumed an existing session.
+    async fn peer_connect(&self, session_id: i64, addr: SocketAddr, resumed: bool);
+
+    /// Saves a session to the `SessionStore`.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    async fn save_session(&self, session_id: i64);
+
+    /// Subscribes a token to a topic on request of its client, if the controller allows it.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of the token.
+    /// * `topic` - The topic name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found and the subscription allowed.
+    async fn client_subscribe(&self, session_id: i64, topic: String) -> bool;
+}
+
+#[async_trait::async_trait]
+impl<T: ICreateController + 'static> ITokenManager<T::Controller> for Actor<AsyncTokenManager<T>> {
+    #[inline]
+    async fn get_token(&self, session_id: i64) -> Option<NetxToken<T::Controller>> {
+        self.inner_call(|inner| async move { inner.get().get_token(session_id) })
+            .await
+    }
+
+    #[inline]
+    async fn get_all_tokens(&self) -> Vec<NetxToken<T::Controller>> {
+        self.inner_call(|inner| async move { inner.get().get_all_tokens() })
+            .await
+    }
+
+    #[inline]
+    async fn kick(&self, session_id: i64) -> bool {
+        match self.get_token(session_id).await {
+            Some(token) => {
+                token.kick_peer(false).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    async fn expire(&self, session_id: i64) -> bool {
+        match self.get_token(session_id).await {
+            Some(token) => {
+                // takes the peer so the closing connection does not disconnect the removed token
+                token.kick_peer(true).await;
+                self.inner_call(
+                    |inner| async move { inner.get_mut().remove_token(session_id).await },
+                )
+                .await
+            }
+            None => false,
+        }
+    }
+
+    #[inline]
+    async fn bind_key(&self, session_id: i64, key: &str) -> bool {
+        let key = key.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().bind_key(session_id, key) })
+            .await
+    }
+
+    #[inline]
+    async fn unbind_key(&self, session_id: i64, key: &str) -> bool {
+        let key = key.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().keys.unbind(session_id, &key) })
+            .await
+    }
+
+    #[inline]
+    async fn get_tokens_by_key(&self, key: &str) -> Vec<NetxToken<T::Controller>> {
+        let key = key.to_string();
+        self.inner_call(|inner| async move { inner.get().get_tokens_by_key(&key) })
+            .await
+    }
+
+    #[inline]
+    async fn get_keys(&self, session_id: i64) -> Vec<String> {
+        self.inner_call(|inner| async move { inner.get().get_keys(session_id) })
+            .await
+    }
+
+    #[inline]
+    async fn join_group(&self, session_id: i64, group: &str) -> bool {
+        let group = group.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().join_group(session_id, group) })
+            .await
+    }
+
+    #[inline]
+    async fn leave_group(&self, session_id: i64, group: &str) -> bool {
+        let group = group.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().groups.unbind(session_id, &group) })
+            .await
+    }
+
+    #[inline]
+    async fn get_group_members(&self, group: &str) -> Vec<NetxToken<T::Controller>> {
+        let group = group.to_string();
+        self.inner_call(|inner| async move { inner.get().get_group_members(&group) })
+            .await
+    }
+
+    #[inline]
+    async fn get_groups(&self, session_id: i64) -> Vec<String> {
+        self.inner_call(|inner| async move { inner.get().get_groups(session_id) })
+            .await
+    }
+
+    #[inline]
+    async fn broadcast(
+        &self,
+        group: &str,
+        buff: Data,
+        except: Option<i64>,
+    ) -> Vec<(i64, crate::error::Error)> {
+        run_all(self.get_group_members(group).await, buff, except).await
+    }
+
+    #[inline]
+    async fn subscribe(&self, session_id: i64, topic: &str) -> bool {
+        let topic = topic.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().subscribe(session_id, topic) })
+            .await
+    }
+
+    #[inline]
+    async fn unsubscribe(&self, session_id: i64, topic: &str) -> bool {
+        let topic = topic.to_string();
+        self.inner_call(|inner| async move { inner.get_mut().topics.unbind(session_id, &topic) })
+            .await
+    }
+
+    #[inline]
+    async fn get_subscribers(&self, topic: &str) -> Vec<NetxToken<T::Controller>> {
+        let topic = topic.to_string();
+        self.inner_call(|inner| async move { inner.get().get_subscribers(&topic) })
+            .await
+    }
+
+    #[inline]
+    async fn get_topics(&self, session_id: i64) -> Vec<String> {
+        self.inner_call(|inner| async move { inner.get().get_topics(session_id) })
+            .await
+    }
+
+    #[inline]
+    async fn publish(&self, topic: &str, buff: Data) -> Vec<(i64, crate::error::Error)> {
+        run_all(self.get_subscribers(topic).await, buff, None).await
+    }
+
+    async fn call_all(
+        &self,
+        tokens: Vec<NetxToken<T::Controller>>,
+        buff: Data,
+        timeout: Duration,
+    ) -> Vec<(i64, crate::error::Result<RetResult>)> {
+        let deadline = Instant::now() + timeout;
+        let buff = buff.into_inner();
+        let calls = tokens
+            .into_iter()
+            .map(|token| {
+                let session_id = token.get_session_id();
+                let serial = token.new_serial();
+                let mut data = Data::from(buff.clone());
+                let call = match set_call_serial(&mut data, serial) {
+                    Ok(()) => Ok(tokio::spawn(async move {
+                        tokio::time::timeout_at(deadline, token.call(serial, data)).await
+                    })),
+                    Err(err) => Err(crate::error::Error::Error(err)),
+                };
+                (session_id, serial, call)
+            })
+            .collect::<Vec<_>>();
+
+        let mut results = Vec::with_capacity(calls.len());
+        for (session_id, serial, call) in calls {
+            let result = match call {
+                Ok(call) => match call.await {
+                    Ok(Ok(result)) => result,
+                    Ok(Err(_)) => Err(crate::error::Error::SerialTimeOut(serial)),
+                    Err(err) => Err(crate::error::Error::Error(err.into())),
+                },
+                Err(err) => Err(err),
+            };
+            results.push((session_id, result));
+        }
+        results
+    }
+
+    #[inline]
+    async fn get_sessions(&self) -> Vec<SessionInfo> {
+        let mut sessions = Vec::new();
+        for token in self.get_all_tokens().await {
+            sessions.push(token.get_session_info().await);
+        }
+        sessions
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: ICreateController + 'static> IAsyncTokenManager<T::Controller>
+    for Actor<AsyncTokenManager<T>>
+{
+    #[inline]
     async fn check_tokens_request_timeout(&self) {
         unsafe { self.deref_inner().check_tokens_request_timeout().await }
     }
@@ -261,14 +1220,355 @@ impl<T: ICreateController + 'static> IAsyncTokenManager<T::Controller>
     }
 
     #[inline]
-    async fn peer_disconnect(&self, session_id: i64) {
+    async fn peer_disconnect(&self, session_id: i64, addr: SocketAddr) {
         self.inner_call(|inner| async move {
             log::debug!("token {} start disconnect clear ", session_id);
-            inner
-                .get_mut()
-                .request_disconnect_clear_queue
-                .push_front((session_id, Instant::now()));
+            let manager = inner.get_mut();
+            // an expired token is already removed with its stored session
+            let Some(token) = manager.dict.get(&session_id) else {
+                return;
+            };
+            let expires_at =
+                SystemTime::now() + Duration::from_millis(manager.session_save_time as u64);
+            token.set_expires_at(Some(expires_at)).await;
+            manager.save_session(token).await;
+            manager.schedule_expire(session_id, Some(expires_at));
+            manager.send_event(session_id, Some(addr), SessionEventKind::Disconnected);
+        })
+        .await
+    }
+
+    #[inline]
+    async fn peer_connect(&self, session_id: i64, addr: SocketAddr, resumed: bool) {
+        self.inner_call(|inner| async move {
+            let manager = inner.get();
+            if let Some(token) = manager.dict.get(&session_id) {
+                token.set_expires_at(None).await;
+                manager.save_session(token).await;
+            }
+            let kind = if resumed {
+                SessionEventKind::Resumed
+            } else {
+                SessionEventKind::Connected
+            };
+            manager.send_event(session_id, Some(addr), kind);
+        })
+        .await
+    }
+
+    #[inline]
+    async fn save_session(&self, session_id: i64) {
+        self.inner_call(|inner| async move {
+            let manager = inner.get();
+            if let Some(token) = manager.dict.get(&session_id) {
+                manager.save_session(token).await;
+            }
         })
         .await
     }
+
+    #[inline]
+    async fn client_subscribe(&self, session_id: i64, topic: String) -> bool {
+        self.inner_call(|inner| async move {
+            let manager = inner.get_mut();
+            manager.impl_controller.allow_subscribe(session_id, &topic)
+                && manager.subscribe(session_id, topic)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::session_store::{FileSessionStore, MemorySessionStore};
+    use anyhow::Result;
+    use data_rw::DataOwnedReader;
+
+    struct TestController;
+
+    impl IController for TestController {
+        async fn call(&self, _tt: u8, _cmd_tag: i32, _dr: DataOwnedReader) -> Result<RetResult> {
+            Ok(RetResult::success())
+        }
+    }
+
+    struct TestCreateController;
+
+    impl ICreateController for TestCreateController {
+        type Controller = TestController;
+
+        fn create_controller(
+            &self,
+            _token: NetxToken<Self::Controller>,
+        ) -> Result<Arc<Self::Controller>> {
+            Ok(Arc::new(TestController))
+        }
+    }
+
+    fn new_manager(session_save_time: u32) -> TokenManager<TestCreateController> {
+        AsyncTokenManager::new(
+            TestCreateController,
+            5000,
+            session_save_time,
+            16,
+            OutboxOverflow::DropOldest,
+            false,
+        )
+    }
+
+    /// Loads a session through the store queue, after the queued saves and removes.
+    async fn load(
+        manager: &TokenManager<TestCreateController>,
+        session_id: i64,
+    ) -> Result<Option<SessionRecord>> {
+        unsafe {
+            let manager = manager.deref_inner();
+            let store = manager.session_store.as_ref();
+            store.expect("session store not set").load(session_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn expire_sends_one_terminal_event_and_clears_store() -> Result<()> {
+        let manager = new_manager(5000);
+        let store = Arc::new(MemorySessionStore::default());
+        manager.set_session_store(store.clone()).await?;
+        let mut events = unsafe { manager.deref_inner().subscribe_events() };
+
+        let token = manager.create_token(Arc::downgrade(&manager)).await?;
+        let session_id = token.get_session_id();
+        let addr: SocketAddr = "127.0.0.1:1000".parse()?;
+        manager.peer_connect(session_id, addr, false).await;
+        assert!(load(&manager, session_id).await?.is_some());
+
+        assert!(manager.expire(session_id).await);
+        // the connection closing after the expire must not bring the session back
+        manager.peer_disconnect(session_id, addr).await;
+
+        let kinds = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| event.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                SessionEventKind::Created,
+                SessionEventKind::Connected,
+                SessionEventKind::Expired
+            ]
+        );
+        assert!(load(&manager, session_id).await?.is_none());
+        assert!(store.load(session_id).await?.is_none());
+        assert!(manager.get_token(session_id).await.is_none());
+        assert!(!manager.expire(session_id).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sessions_are_not_stored_without_store() -> Result<()> {
+        let manager = new_manager(5000);
+        let token = manager.create_token(Arc::downgrade(&manager)).await?;
+        assert!(unsafe { manager.deref_inner().session_store.is_none() });
+        let resume_token = vec![1; RESUME_TOKEN_LEN];
+        let session_id = token.get_session_id() ^ 1;
+        assert!(matches!(
+            manager
+                .resume_session(session_id, &resume_token, Arc::downgrade(&manager))
+                .await?,
+            Resume::NotFound
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn wrong_resume_token_does_not_restore_session() -> Result<()> {
+        let manager = new_manager(5000);
+        let store = Arc::new(MemorySessionStore::default());
+        let resume_token = vec![7; RESUME_TOKEN_LEN];
+        store
+            .save(SessionRecord {
+                session_id: 7,
+                resume_token: resume_token.clone(),
+                expires_at: Some(SystemTime::now() + Duration::from_secs(60)),
+                state: b"state".to_vec(),
+            })
+            .await?;
+        manager.set_session_store(store).await?;
+
+        let mut wrong = resume_token.clone();
+        wrong[RESUME_TOKEN_LEN - 1] = 8;
+        for presented in [wrong, Vec::new(), resume_token[1..].to_vec()] {
+            assert!(matches!(
+                manager
+                    .resume_session(7, &presented, Arc::downgrade(&manager))
+                    .await?,
+                Resume::Mismatch
+            ));
+            assert!(manager.get_token(7).await.is_none());
+        }
+
+        let token = match manager
+            .resume_session(7, &resume_token, Arc::downgrade(&manager))
+            .await?
+        {
+            Resume::Resumed(token) => token,
+            _ => panic!("session 7 not resumed"),
+        };
+        assert_eq!(token.get_session_state().await, b"state");
+        assert!(manager.get_token(7).await.is_some());
+        // the token in memory is checked the same way
+        assert!(matches!(
+            manager
+                .resume_session(7, &[0; RESUME_TOKEN_LEN], Arc::downgrade(&manager))
+                .await?,
+            Resume::Mismatch
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restored_session_is_removed_when_not_resumed() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("netx_session_test_{}", std::process::id()));
+        let store = Arc::new(FileSessionStore::open(&dir)?);
+        store
+            .save(SessionRecord {
+                session_id: 7,
+                resume_token: vec![1; RESUME_TOKEN_LEN],
+                expires_at: None,
+                state: Vec::new(),
+            })
+            .await?;
+
+        let manager = new_manager(100);
+        manager.set_session_store(store.clone()).await?;
+        let record = load(&manager, 7).await?.expect("session 7 not restored");
+        assert!(record.expires_at.is_some());
+
+        sleep(Duration::from_millis(400)).await;
+        assert!(store.load(7).await?.is_none());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restored_session_is_kept_until_it_expires() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("netx_session_expiry_{}", std::process::id()));
+        let store = Arc::new(FileSessionStore::open(&dir)?);
+        store
+            .save(SessionRecord {
+                session_id: 8,
+                resume_token: vec![1; RESUME_TOKEN_LEN],
+                expires_at: Some(SystemTime::now() + Duration::from_millis(800)),
+                state: Vec::new(),
+            })
+            .await?;
+
+        let manager = new_manager(100);
+        manager.set_session_store(store.clone()).await?;
+        // longer than session_save_time, but before the stored expiry
+        sleep(Duration::from_millis(300)).await;
+        assert!(store.load(8).await?.is_some());
+
+        sleep(Duration::from_millis(900)).await;
+        assert!(store.load(8).await?.is_none());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keys_index_tokens_until_they_expire() -> Result<()> {
+        let manager = new_manager(5000);
+        let first = manager.create_token(Arc::downgrade(&manager)).await?;
+        let second = manager.create_token(Arc::downgrade(&manager)).await?;
+        let (first, second) = (first.get_session_id(), second.get_session_id());
+
+        assert!(manager.bind_key(first, "user:1").await);
+        // binding again is not an error, the key stays bound once
+        assert!(manager.bind_key(first, "user:1").await);
+        assert!(manager.bind_key(first, "device:a").await);
+        assert!(manager.bind_key(second, "user:1").await);
+        let removed = manager.create_token(Arc::downgrade(&manager)).await?;
+        assert!(manager.expire(removed.get_session_id()).await);
+        assert!(!manager.bind_key(removed.get_session_id(), "user:1").await);
+        assert_eq!(manager.get_keys(first).await.len(), 2);
+
+        let mut sessions = manager
+            .get_tokens_by_key("user:1")
+            .await
+            .iter()
+            .map(|token| token.get_session_id())
+            .collect::<Vec<_>>();
+        sessions.sort();
+        let mut expected = vec![first, second];
+        expected.sort();
+        assert_eq!(sessions, expected);
+
+        assert!(manager.unbind_key(second, "user:1").await);
+        assert!(!manager.unbind_key(second, "user:1").await);
+        assert_eq!(manager.get_tokens_by_key("user:1").await.len(), 1);
+
+        assert!(manager.expire(first).await);
+        assert!(manager.get_tokens_by_key("user:1").await.is_empty());
+        assert!(manager.get_tokens_by_key("device:a").await.is_empty());
+        assert!(manager.get_keys(first).await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broadcast_reaches_group_members_except_one() -> Result<()> {
+        let manager = new_manager(5000);
+        let first = manager.create_token(Arc::downgrade(&manager)).await?;
+        let second = manager.create_token(Arc::downgrade(&manager)).await?;
+        let other = manager.create_token(Arc::downgrade(&manager)).await?;
+        for token in [&first, &second] {
+            assert!(manager.join_group(token.get_session_id(), "room").await);
+        }
+        assert_eq!(manager.get_group_members("room").await.len(), 2);
+
+        // disconnected members buffer the message in their outbox
+        let failed = manager
+            .broadcast("room", Data::from(vec![1]), Some(first.get_session_id()))
+            .await;
+        assert!(failed.is_empty());
+        assert_eq!(first.get_outbox_stats().await.queued, 0);
+        assert_eq!(second.get_outbox_stats().await.queued, 1);
+        assert_eq!(other.get_outbox_stats().await.queued, 0);
+
+        assert!(manager.leave_group(second.get_session_id(), "room").await);
+        assert!(manager.get_groups(second.get_session_id()).await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broadcast_returns_the_members_it_could_not_reach() -> Result<()> {
+        let manager: TokenManager<TestCreateController> = AsyncTokenManager::new(
+            TestCreateController,
+            5000,
+            5000,
+            0,
+            OutboxOverflow::DropOldest,
+            false,
+        );
+        let mut members = Vec::new();
+        for _ in 0..3 {
+            let token = manager.create_token(Arc::downgrade(&manager)).await?;
+            manager.join_group(token.get_session_id(), "room").await;
+            members.push(token.get_session_id());
+        }
+        let mut failed = manager
+            .broadcast("room", Data::from(vec![1]), None)
+            .await
+            .into_iter()
+            .map(|(session_id, err)| {
+                assert!(
+                    matches!(err, crate::error::Error::TokenDisconnect(id) if id == session_id)
+                );
+                session_id
+            })
+            .collect::<Vec<_>>();
+        failed.sort();
+        members.sort();
+        assert_eq!(failed, members);
+        Ok(())
+    }
 }
@@ -0,0 +1,277 @@
+use crate::ServerOption;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+use tokio::time::Instant;
+
+/// The capacity of the audit event channel, slow subscribers lose the oldest events.
+const AUTH_EVENT_CAPACITY: usize = 1024;
+
+/// Pruning of idle entries starts once this many addresses are tracked.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// An audit event for a handshake.
+#[derive(Clone, Debug)]
+pub struct AuthEvent {
+    /// The address of the remote client, from the PROXY protocol header when enabled.
+    pub addr: SocketAddr,
+    /// The time of the event.
+    pub time: SystemTime,
+    /// What happened.
+    pub kind: AuthEventKind,
+}
+
+/// The kind of an `AuthEvent`.
+#[derive(Clone, Debug)]
+pub enum AuthEventKind {
+    /// The handshake succeeded.
+    Success {
+        /// The session ID of the token.
+        session_id: i64,
+        /// The identity mapped from the client certificate.
+        identity: Option<String>,
+    },
+    /// The handshake failed, like a wrong `verify_key` or `service_name`.
+    Failure {
+        /// The reason of the failure.
+        reason: String,
+        /// The number of consecutive failures from this IP address.
+        failures: u32,
+    },
+    /// The IP address is locked out after too many failures.
+    LockedOut {
+        /// The remaining lockout time.
+        remaining: Duration,
+    },
+}
+
+/// Failures of one IP address.
+struct FailureEntry {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Tracks failed handshakes per IP address and locks out repeat offenders.
+pub(crate) struct AuthGuard {
+    failure_threshold: u32,
+    lockout_time: Duration,
+    max_lockout_time: Duration,
+    entries: Mutex<HashMap<IpAddr, FailureEntry>>,
+    events: broadcast::Sender<AuthEvent>,
+}
+
+impl AuthGuard {
+    /// Creates the guard from the server options.
+    ///
+    /// # Arguments
+    ///
+    /// * `option` - The server options.
+    pub(crate) fn new(option: &ServerOption) -> AuthGuard {
+        let (events, _) = broadcast::channel(AUTH_EVENT_CAPACITY);
+        AuthGuard {
+            failure_threshold: option.auth_failure_threshold,
+            lockout_time: Duration::from_millis(option.auth_lockout_time as u64),
+            max_lockout_time: Duration::from_millis(option.auth_max_lockout_time as u64),
+            entries: Default::default(),
+            events,
+        }
+    }
+
+    /// Subscribes to the audit events.
+    #[inline]
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<AuthEvent> {
+        self.events.subscribe()
+    }
+
+    /// Sends an audit event, it is dropped if nobody is subscribed.
+    #[inline]
+    fn send_event(&self, addr: SocketAddr, kind: AuthEventKind) {
+        let _ = self.events.send(AuthEvent {
+            addr,
+            time: SystemTime::now(),
+            kind,
+        });
+    }
+
+    /// Checks whether the address is locked out.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the remote client.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the address is locked out.
+    pub(crate) fn check(&self, addr: SocketAddr) -> Result<()> {
+        let remaining = {
+            let entries = self.entries.lock().unwrap();
+            entries
+                .get(&addr.ip())
+                .and_then(|entry| entry.locked_until)
+                .and_then(|until| until.checked_duration_since(Instant::now()))
+        };
+        if let Some(remaining) = remaining {
+            log::warn!("addr:{} locked out for {:?}", addr, remaining);
+            self.send_event(addr, AuthEventKind::LockedOut { remaining });
+            bail!("addr:{} locked out", addr)
+        }
+        Ok(())
+    }
+
+    /// Records a failed handshake, locking the address out once the threshold is reached.
+    ///
+    /// Each failure past the threshold doubles the lockout time, up to `auth_max_lockout_time`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the remote client.
+    /// * `reason` - The reason of the failure.
+    pub(crate) fn failure(&self, addr: SocketAddr, reason: &str) {
+        let now = Instant::now();
+        let failures = {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.len() >= PRUNE_THRESHOLD {
+                let max_lockout_time = self.max_lockout_time;
+                entries
+                    .retain(|_, entry| now.duration_since(entry.last_failure) < max_lockout_time);
+            }
+            let entry = entries.entry(addr.ip()).or_insert(FailureEntry {
+                failures: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+            // forget old failures once a full lockout period passed without new ones
+            if now.duration_since(entry.last_failure) >= self.max_lockout_time {
+                entry.failures = 0;
+            }
+            entry.failures += 1;
+            entry.last_failure = now;
+            if self.failure_threshold > 0 && entry.failures >= self.failure_threshold {
+                let exponent = (entry.failures - self.failure_threshold).min(31);
+                let lockout = self
+                    .lockout_time
+                    .checked_mul(1 << exponent)
+                    .unwrap_or(self.max_lockout_time)
+                    .min(self.max_lockout_time);
+                entry.locked_until = Some(now + lockout);
+            }
+            entry.failures
+        };
+        log::warn!(
+            "addr:{} handshake failed:{} failures:{}",
+            addr,
+            reason,
+            failures
+        );
+        self.send_event(
+            addr,
+            AuthEventKind::Failure {
+                reason: reason.to_string(),
+                failures,
+            },
+        );
+    }
+
+    /// Records a successful handshake, clearing the failures of the address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the remote client.
+    /// * `session_id` - The session ID of the token.
+    /// * `identity` - The identity mapped from the client certificate.
+    pub(crate) fn success(&self, addr: SocketAddr, session_id: i64, identity: Option<String>) {
+        self.entries.lock().unwrap().remove(&addr.ip());
+        self.send_event(
+            addr,
+            AuthEventKind::Success {
+                session_id,
+                identity,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_guard(threshold: u32) -> AuthGuard {
+        let mut option = ServerOption::new("127.0.0.1:0", "test", "123");
+        option.auth_failure_threshold = threshold;
+        option.auth_lockout_time = 1000;
+        option.auth_max_lockout_time = 5000;
+        AuthGuard::new(&option)
+    }
+
+    /// Gets the lockout time set by the last failure of the address.
+    fn lockout(guard: &AuthGuard, addr: SocketAddr) -> Option<Duration> {
+        let entries = guard.entries.lock().unwrap();
+        let entry = entries.get(&addr.ip())?;
+        Some(entry.locked_until? - entry.last_failure)
+    }
+
+    #[test]
+    fn lockout_starts_at_threshold_and_doubles_up_to_max() {
+        let guard = new_guard(3);
+        let addr: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        let mut events = guard.subscribe();
+
+        guard.failure(addr, "bad key");
+        guard.failure(addr, "bad key");
+        assert_eq!(lockout(&guard, addr), None);
+        assert!(guard.check(addr).is_ok());
+
+        let expected = [1000, 2000, 4000, 5000, 5000];
+        for millis in expected {
+            guard.failure(addr, "bad key");
+            assert_eq!(lockout(&guard, addr), Some(Duration::from_millis(millis)));
+        }
+        assert!(guard.check(addr).is_err());
+        // another port of the same ip is locked out too
+        assert!(guard.check("10.0.0.1:2000".parse().unwrap()).is_err());
+        assert!(guard.check("10.0.0.2:1000".parse().unwrap()).is_ok());
+
+        for failures in 1..=7 {
+            match events.try_recv().unwrap().kind {
+                AuthEventKind::Failure {
+                    failures: count, ..
+                } => assert_eq!(count, failures),
+                kind => panic!("unexpected event:{:?}", kind),
+            }
+        }
+        assert!(matches!(
+            events.try_recv().unwrap().kind,
+            AuthEventKind::LockedOut { .. }
+        ));
+    }
+
+    #[test]
+    fn success_resets_failures() {
+        let guard = new_guard(2);
+        let addr: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        guard.failure(addr, "bad key");
+        guard.failure(addr, "bad key");
+        assert!(guard.check(addr).is_err());
+
+        guard.success(addr, 1, None);
+        assert!(guard.check(addr).is_ok());
+        guard.failure(addr, "bad key");
+        assert_eq!(lockout(&guard, addr), None);
+        assert!(guard.check(addr).is_ok());
+    }
+
+    #[test]
+    fn zero_threshold_never_locks_out() {
+        let guard = new_guard(0);
+        let addr: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        for _ in 0..100 {
+            guard.failure(addr, "bad key");
+        }
+        assert_eq!(lockout(&guard, addr), None);
+        assert!(guard.check(addr).is_ok());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+
+/// A many-to-many index between names and session IDs, like application keys or groups.
+#[derive(Default)]
+pub(crate) struct Bindings {
+    /// The sessions bound to each name.
+    sessions: HashMap<String, HashSet<i64>>,
+    /// The names bound to each session.
+    names: HashMap<i64, HashSet<String>>,
+}
+
+impl Bindings {
+    /// Binds a name to a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    /// * `name` - The name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the name was not bound to the session yet.
+    #[inline]
+    pub(crate) fn bind(&mut self, session_id: i64, name: String) -> bool {
+        self.sessions
+            .entry(name.clone())
+            .or_default()
+            .insert(session_id);
+        self.names.entry(session_id).or_default().insert(name)
+    }
+
+    /// Unbinds a name from a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    /// * `name` - The name.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the name was bound to the session.
+    pub(crate) fn unbind(&mut self, session_id: i64, name: &str) -> bool {
+        let removed = match self.names.get_mut(&session_id) {
+            Some(names) => {
+                let removed = names.remove(name);
+                if names.is_empty() {
+                    self.names.remove(&session_id);
+                }
+                removed
+            }
+            None => false,
+        };
+        self.remove_session(name, session_id);
+        removed
+    }
+
+    /// Unbinds all names of a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    pub(crate) fn unbind_all(&mut self, session_id: i64) {
+        for name in self.names.remove(&session_id).unwrap_or_default() {
+            self.remove_session(&name, session_id);
+        }
+    }
+
+    /// Gets the sessions bound to a name.
+    #[inline]
+    pub(crate) fn get_sessions(&self, name: &str) -> impl Iterator<Item = &i64> {
+        self.sessions.get(name).into_iter().flatten()
+    }
+
+    /// Gets the names bound to a session.
+    #[inline]
+    pub(crate) fn get_names(&self, session_id: i64) -> Vec<String> {
+        self.names
+            .get(&session_id)
+            .map(|names| names.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes a session from the sessions of a name, dropping the name when it has none left.
+    #[inline]
+    fn remove_session(&mut self, name: &str, session_id: i64) {
+        if let Some(sessions) = self.sessions.get_mut(name) {
+            sessions.remove(&session_id);
+            if sessions.is_empty() {
+                self.sessions.remove(name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sessions(bindings: &Bindings, name: &str) -> Vec<i64> {
+        let mut sessions = bindings.get_sessions(name).copied().collect::<Vec<_>>();
+        sessions.sort();
+        sessions
+    }
+
+    fn names(bindings: &Bindings, session_id: i64) -> Vec<String> {
+        let mut names = bindings.get_names(session_id);
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn bind_indexes_both_ways() {
+        let mut bindings = Bindings::default();
+        assert!(bindings.bind(1, "room".into()));
+        assert!(!bindings.bind(1, "room".into()));
+        assert!(bindings.bind(2, "room".into()));
+        assert!(bindings.bind(1, "lobby".into()));
+
+        assert_eq!(sessions(&bindings, "room"), vec![1, 2]);
+        assert_eq!(sessions(&bindings, "lobby"), vec![1]);
+        assert!(sessions(&bindings, "none").is_empty());
+        assert_eq!(names(&bindings, 1), vec!["lobby", "room"]);
+        assert!(names(&bindings, 3).is_empty());
+    }
+
+    #[test]
+    fn unbind_removes_empty_entries() {
+        let mut bindings = Bindings::default();
+        bindings.bind(1, "room".into());
+        bindings.bind(2, "room".into());
+
+        assert!(bindings.unbind(1, "room"));
+        assert!(!bindings.unbind(1, "room"));
+        assert!(!bindings.unbind(3, "room"));
+        assert_eq!(sessions(&bindings, "room"), vec![2]);
+        assert!(!bindings.names.contains_key(&1));
+
+        assert!(bindings.unbind(2, "room"));
+        assert!(bindings.sessions.is_empty());
+        assert!(bindings.names.is_empty());
+    }
+
+    #[test]
+    fn unbind_all_removes_every_name_of_a_session() {
+        let mut bindings = Bindings::default();
+        bindings.bind(1, "room".into());
+        bindings.bind(1, "lobby".into());
+        bindings.bind(2, "room".into());
+
+        bindings.unbind_all(1);
+        bindings.unbind_all(3);
+        assert!(names(&bindings, 1).is_empty());
+        assert_eq!(sessions(&bindings, "room"), vec![2]);
+        assert!(!bindings.sessions.contains_key("lobby"));
+    }
+}
@@ -0,0 +1,1046 @@
+use crate::async_token::{IAsyncToken, NetxToken};
+use crate::async_token_manager::{set_call_serial, ITokenManager};
+use crate::impl_server::SpecialFunctionTag;
+use crate::server::bindings::Bindings;
+use crate::{
+    IController, ICreateController, NetXServer, RetResult, ServerOption, SessionEventKind,
+    TlsConfig,
+};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use data_rw::{Data, DataOwnedReader};
+use netxclient::call;
+use netxclient::prelude::{DefaultSessionStore, INetXClient, NetXClient, NetxClientArc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::time::{sleep, Duration};
+
+/// How often the links to the other nodes are checked and reconnected.
+const LINK_CHECK_INTERVAL: Duration = Duration::from_secs(3);
+
+/// The commands of the cluster links.
+#[derive(Copy, Clone)]
+enum ClusterTag {
+    /// Replaces the directory entries of a node.
+    Sync = 1,
+    /// A session was created on a node.
+    SessionUp = 2,
+    /// A session was removed from a node.
+    SessionDown = 3,
+    /// An application key was bound to a session of a node.
+    KeyBound = 4,
+    /// An application key was unbound from a session of a node.
+    KeyUnbound = 5,
+    /// Runs a call on a local session.
+    ForwardRun = 10,
+    /// Calls a local session and returns the result.
+    ForwardCall = 11,
+    /// Broadcasts to a local group.
+    ForwardBroadcast = 12,
+    /// Publishes to a local topic.
+    ForwardPublish = 13,
+}
+
+/// The result of a forwarded call: is error, error id, message and the encoded arguments.
+type ForwardResult = (bool, i32, String, Vec<Vec<u8>>);
+
+/// Another node of the cluster.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ClusterPeer {
+    /// The unique name of the node.
+    pub node_id: String,
+    /// The address of the cluster link server of the node.
+    pub addr: String,
+}
+
+/// Configuration of the cluster mode.
+///
+/// Any node holding the `verify_key` can run and call the sessions of this node through its link,
+/// set `tls` with a required client certificate and `link_tls` with that certificate
+/// to only accept the links of known nodes.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct ClusterOption {
+    /// The unique name of this node.
+    pub node_id: String,
+    /// The address the cluster link server of this node listens on.
+    pub addr: String,
+    /// The service name of the cluster links, the same on every node.
+    pub service_name: String,
+    /// The key of the cluster links, the same on every node.
+    pub verify_key: String,
+    /// The timeout in milliseconds of requests between nodes.
+    #[serde(default = "default_request_out_time")]
+    pub request_out_time: u32,
+    /// The other nodes of the cluster.
+    pub peers: Vec<ClusterPeer>,
+    /// The TLS configuration of the cluster link server, plain TCP by default.
+    #[serde(skip, default = "default_tls")]
+    pub tls: TlsConfig,
+    /// The TLS configuration of the links to the other nodes, plain TCP by default.
+    #[serde(skip, default = "default_link_tls")]
+    pub link_tls: netxclient::prelude::TlsConfig,
+}
+
+impl fmt::Debug for ClusterOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClusterOption")
+            .field("node_id", &self.node_id)
+            .field("addr", &self.addr)
+            .field("service_name", &self.service_name)
+            .field("request_out_time", &self.request_out_time)
+            .field("peers", &self.peers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Default value for `ClusterOption::request_out_time`.
+#[inline]
+fn default_request_out_time() -> u32 {
+    5000
+}
+
+/// Default value for `ClusterOption::tls`.
+#[inline]
+fn default_tls() -> TlsConfig {
+    TlsConfig::None
+}
+
+/// Default value for `ClusterOption::link_tls`.
+#[inline]
+fn default_link_tls() -> netxclient::prelude::TlsConfig {
+    netxclient::prelude::TlsConfig::None
+}
+
+impl ClusterOption {
+    /// Creates a new `ClusterOption` without peers.
+    ///
+    /// # Arguments
+    ///
+    /// * `node_id` - The unique name of this node.
+    /// * `addr` - The address the cluster link server listens on.
+    /// * `service_name` - The service name of the cluster links.
+    /// * `verify_key` - The key of the cluster links.
+    ///
+    /// # Returns
+    ///
+    /// A `ClusterOption` instance.
+    #[inline]
+    pub fn new(node_id: &str, addr: &str, service_name: &str, verify_key: &str) -> ClusterOption {
+        ClusterOption {
+            node_id: node_id.to_string(),
+            addr: addr.to_string(),
+            service_name: service_name.to_string(),
+            verify_key: verify_key.to_string(),
+            request_out_time: default_request_out_time(),
+            peers: Vec::new(),
+            tls: default_tls(),
+            link_tls: default_link_tls(),
+        }
+    }
+}
+
+/// The sessions and application keys of the other nodes.
+#[derive(Default)]
+struct Directory {
+    /// The node owning each remote session.
+    sessions: HashMap<i64, String>,
+    /// The application keys bound to remote sessions.
+    keys: Bindings,
+}
+
+impl Directory {
+    /// Replaces the entries of a node.
+    fn sync(&mut self, node_id: &str, sessions: Vec<i64>, keys: Vec<(String, i64)>) {
+        self.remove_node(node_id);
+        for session_id in sessions {
+            self.sessions.insert(session_id, node_id.to_string());
+        }
+        for (key, session_id) in keys {
+            if self.sessions.contains_key(&session_id) {
+                self.keys.bind(session_id, key);
+            }
+        }
+    }
+
+    /// Removes the entries of a node.
+    fn remove_node(&mut self, node_id: &str) {
+        let sessions = self
+            .sessions
+            .iter()
+            .filter(|(_, node)| node.as_str() == node_id)
+            .map(|(session_id, _)| *session_id)
+            .collect::<Vec<_>>();
+        for session_id in sessions {
+            self.remove_session(session_id);
+        }
+    }
+
+    /// Removes a session and its keys.
+    fn remove_session(&mut self, session_id: i64) {
+        self.sessions.remove(&session_id);
+        self.keys.unbind_all(session_id);
+    }
+
+    /// Returns `true` if the session is owned by the node.
+    #[inline]
+    fn is_owner(&self, node_id: &str, session_id: i64) -> bool {
+        self.sessions.get(&session_id).map(String::as_str) == Some(node_id)
+    }
+
+    /// Removes a session announced down by a node, unless another node owns it now.
+    fn session_down(&mut self, node_id: &str, session_id: i64) {
+        if self.is_owner(node_id, session_id) {
+            self.remove_session(session_id);
+        }
+    }
+
+    /// Binds or unbinds a key announced by a node, if the node owns the session.
+    fn key_changed(&mut self, node_id: &str, session_id: i64, key: String, bound: bool) {
+        if !self.is_owner(node_id, session_id) {
+            return;
+        }
+        if bound {
+            self.keys.bind(session_id, key);
+        } else {
+            self.keys.unbind(session_id, &key);
+        }
+    }
+}
+
+/// Links `NetXServer` nodes so the sessions of every node can be reached from any node.
+///
+/// Each node runs a cluster link server and connects to the link servers of the other
+/// nodes with a `NetXClient`. The nodes share a directory of their session IDs and
+/// application keys, and forward runs, calls, broadcasts and publishes to the owning node.
+pub struct Cluster<C: IController + 'static> {
+    option: ClusterOption,
+    manager: Weak<dyn ITokenManager<C>>,
+    directory: RwLock<Directory>,
+    links: HashMap<String, NetxClientArc<DefaultSessionStore>>,
+    link_server: Mutex<Option<NetXServer<ClusterCreateController<C>>>>,
+}
+
+impl<C: IController + 'static> Cluster<C> {
+    /// Starts the cluster mode of a server.
+    ///
+    /// # Arguments
+    ///
+    /// * `option` - The cluster configuration.
+    /// * `server` - The server whose sessions are shared.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the cluster.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the cluster link server cannot be started.
+    pub async fn start<T: ICreateController<Controller = C> + 'static>(
+        option: ClusterOption,
+        server: &NetXServer<T>,
+    ) -> Result<Arc<Cluster<C>>> {
+        let links = option
+            .peers
+            .iter()
+            .map(|peer| {
+                let link = NetXClient::new_with_tls(
+                    netxclient::prelude::ServerOption::new(
+                        peer.addr.clone(),
+                        option.service_name.clone(),
+                        option.verify_key.clone(),
+                        option.request_out_time,
+                    ),
+                    DefaultSessionStore::default(),
+                    option.link_tls.clone(),
+                );
+                (peer.node_id.clone(), link)
+            })
+            .collect();
+        let cluster = Arc::new(Cluster {
+            option,
+            manager: server.get_token_manager(),
+            directory: RwLock::new(Directory::default()),
+            links,
+            link_server: Mutex::new(None),
+        });
+
+        for (node_id, link) in cluster.links.iter() {
+            link.init(ClusterLinkController {
+                node_id: node_id.clone(),
+                cluster: Arc::downgrade(&cluster),
+            })
+            .await;
+        }
+
+        let mut link_option = ServerOption::new(
+            &cluster.option.addr,
+            &cluster.option.service_name,
+            &cluster.option.verify_key,
+        );
+        link_option.request_out_time = cluster.option.request_out_time;
+        let link_server = NetXServer::new_with_tls(
+            cluster.option.tls.clone(),
+            link_option,
+            ClusterCreateController {
+                cluster: Arc::downgrade(&cluster),
+            },
+        )
+        .await;
+        link_server.start().await?;
+        *cluster.link_server.lock().unwrap() = Some(link_server);
+
+        Self::start_check_links(Arc::downgrade(&cluster));
+        Self::start_announce(Arc::downgrade(&cluster), server.subscribe_session_events());
+        log::info!("cluster node {} started", cluster.option.node_id);
+        Ok(cluster)
+    }
+
+    /// Gets the name of this node.
+    #[inline]
+    pub fn get_node_id(&self) -> &str {
+        &self.option.node_id
+    }
+
+    /// Finds the node owning a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    ///
+    /// # Returns
+    ///
+    /// The node ID, or `None` if no node has the session.
+    pub async fn locate(&self, session_id: i64) -> Option<String> {
+        if self.get_local_token(session_id).await.is_some() {
+            return Some(self.option.node_id.clone());
+        }
+        self.directory
+            .read()
+            .unwrap()
+            .sessions
+            .get(&session_id)
+            .cloned()
+    }
+
+    /// Binds an application key to a session of this node and announces it to the other nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of a local token.
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the token was found.
+    pub async fn bind_key(&self, session_id: i64, key: &str) -> bool {
+        let bound = match self.manager.upgrade() {
+            Some(manager) => manager.bind_key(session_id, key).await,
+            None => false,
+        };
+        if bound {
+            self.announce(ClusterTag::KeyBound, session_id, Some(key))
+                .await;
+        }
+        bound
+    }
+
+    /// Unbinds an application key from a session of this node and announces it to the other nodes.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID of a local token.
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key was bound to the token.
+    pub async fn unbind_key(&self, session_id: i64, key: &str) -> bool {
+        let unbound = match self.manager.upgrade() {
+            Some(manager) => manager.unbind_key(session_id, key).await,
+            None => false,
+        };
+        if unbound {
+            self.announce(ClusterTag::KeyUnbound, session_id, Some(key))
+                .await;
+        }
+        unbound
+    }
+
+    /// Finds the sessions bound to an application key on every node.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The application key.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the node ID and session ID of each bound session.
+    pub async fn get_sessions_by_key(&self, key: &str) -> Vec<(String, i64)> {
+        let mut sessions = match self.manager.upgrade() {
+            Some(manager) => manager
+                .get_tokens_by_key(key)
+                .await
+                .iter()
+                .map(|token| (self.option.node_id.clone(), token.get_session_id()))
+                .collect(),
+            None => Vec::new(),
+        };
+        let directory = self.directory.read().unwrap();
+        for session_id in directory.keys.get_sessions(key) {
+            if let Some(node_id) = directory.sessions.get(session_id) {
+                sessions.push((node_id.clone(), *session_id));
+            }
+        }
+        sessions
+    }
+
+    /// Runs an encoded call on a session of any node, like `IAsyncToken::run`.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    /// * `buff` - The encoded run call.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the call was sent.
+    pub async fn run(&self, session_id: i64, buff: Data) -> crate::error::Result<()> {
+        match self.route(session_id).await? {
+            Route::Local(token) => token.run(buff).await,
+            Route::Remote(link) => Ok(async {
+                call!(@checkrun link=>ClusterTag::ForwardRun as i32; session_id, buff.into_inner());
+                Ok::<_, anyhow::Error>(())
+            }
+            .await?),
+        }
+    }
+
+    /// Calls a session of any node with an encoded call and waits for the result, like `IAsyncToken::call`.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    /// * `buff` - The encoded call, `call_peer!` encoding with any serial.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `RetResult` of the client.
+    pub async fn call(&self, session_id: i64, buff: Data) -> crate::error::Result<RetResult> {
+        match self.route(session_id).await? {
+            Route::Local(token) => Self::call_local(&token, buff).await,
+            Route::Remote(link) => {
+                let (is_error, error_id, msg, args) = async {
+                    let result: ForwardResult =
+                        call!(link=>ClusterTag::ForwardCall as i32; session_id, buff.into_inner());
+                    Ok::<_, anyhow::Error>(result)
+                }
+                .await?;
+                Ok(RetResult::new(
+                    is_error,
+                    error_id,
+                    msg,
+                    args.into_iter().map(DataOwnedReader::new).collect(),
+                ))
+            }
+        }
+    }
+
+    /// Sends an encoded run call to the members of a group on every node, like `ITokenManager::broadcast`.
+    ///
+    /// A node that cannot be reached is logged and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The group name.
+    /// * `buff` - The encoded run call.
+    /// * `except` - A session not to send to.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and error of each member the call could not be sent to.
+    pub async fn broadcast(
+        &self,
+        group: &str,
+        buff: Data,
+        except: Option<i64>,
+    ) -> Vec<(i64, crate::error::Error)> {
+        let buff = buff.into_inner();
+        let mut failed = match self.manager.upgrade() {
+            Some(manager) => {
+                manager
+                    .broadcast(group, Data::from(buff.clone()), except)
+                    .await
+            }
+            None => Vec::new(),
+        };
+        for (node_id, link) in self.links.iter() {
+            let remote = async {
+                let failed: Vec<(i64, String)> =
+                    call!(link=>ClusterTag::ForwardBroadcast as i32; group, buff.clone(), except);
+                Ok::<_, anyhow::Error>(failed)
+            };
+            Self::collect_remote_failures(node_id, remote, &mut failed).await;
+        }
+        failed
+    }
+
+    /// Sends an encoded run call to the subscribers of a topic on every node, like `ITokenManager::publish`.
+    ///
+    /// A node that cannot be reached is logged and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `buff` - The encoded run call.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and error of each subscriber the call could not be sent to.
+    pub async fn publish(&self, topic: &str, buff: Data) -> Vec<(i64, crate::error::Error)> {
+        let buff = buff.into_inner();
+        let mut failed = match self.manager.upgrade() {
+            Some(manager) => manager.publish(topic, Data::from(buff.clone())).await,
+            None => Vec::new(),
+        };
+        for (node_id, link) in self.links.iter() {
+            let remote = async {
+                let failed: Vec<(i64, String)> =
+                    call!(link=>ClusterTag::ForwardPublish as i32; topic, buff.clone());
+                Ok::<_, anyhow::Error>(failed)
+            };
+            Self::collect_remote_failures(node_id, remote, &mut failed).await;
+        }
+        failed
+    }
+
+    /// Adds the failures reported by a node, or logs why the node could not be reached.
+    async fn collect_remote_failures(
+        node_id: &str,
+        remote: impl Future<Output = Result<Vec<(i64, String)>>>,
+        failed: &mut Vec<(i64, crate::error::Error)>,
+    ) {
+        match remote.await {
+            Ok(remote_failed) => failed.extend(
+                remote_failed
+                    .into_iter()
+                    .map(|(session_id, err)| (session_id, anyhow!(err).into())),
+            ),
+            Err(err) => log::warn!("cluster node {} is unreachable:{}", node_id, err),
+        }
+    }
+
+    /// Gets a token of this node.
+    #[inline]
+    async fn get_local_token(&self, session_id: i64) -> Option<NetxToken<C>> {
+        self.manager.upgrade()?.get_token(session_id).await
+    }
+
+    /// Finds where to send a request for a session.
+    async fn route(&self, session_id: i64) -> crate::error::Result<Route<'_, C>> {
+        if let Some(token) = self.get_local_token(session_id).await {
+            return Ok(Route::Local(token));
+        }
+        let node_id = self
+            .directory
+            .read()
+            .unwrap()
+            .sessions
+            .get(&session_id)
+            .cloned()
+            .ok_or(crate::error::Error::SessionNotFound(session_id))?;
+        let link = self
+            .links
+            .get(&node_id)
+            .with_context(|| format!("cluster node {} is not a peer", node_id))?;
+        Ok(Route::Remote(link))
+    }
+
+    /// Calls a local token with an encoded call, setting the serial of the token.
+    #[inline]
+    async fn call_local(token: &NetxToken<C>, buff: Data) -> crate::error::Result<RetResult> {
+        let mut buff = buff;
+        let serial = token.new_serial();
+        set_call_serial(&mut buff, serial)?;
+        token.call(serial, buff).await
+    }
+
+    /// Sends the sessions and keys of this node to a node, replacing what it knew.
+    async fn sync_to(&self, node_id: &str) -> Result<()> {
+        let link = self.links.get(node_id).context("not found link")?;
+        let manager = self.manager.upgrade().context("manager upgrade fail")?;
+        let mut sessions = Vec::new();
+        let mut keys = Vec::new();
+        for token in manager.get_all_tokens().await {
+            let session_id = token.get_session_id();
+            sessions.push(session_id);
+            for key in manager.get_keys(session_id).await {
+                keys.push((key, session_id));
+            }
+        }
+        call!(@checkrun link=>ClusterTag::Sync as i32; &self.option.node_id, sessions, keys);
+        Ok(())
+    }
+
+    /// Tells the connected nodes about a change of a local session.
+    ///
+    /// Nodes that are not connected get the change with the full sync when they reconnect.
+    async fn announce(&self, tag: ClusterTag, session_id: i64, key: Option<&str>) {
+        for (node_id, link) in self.links.iter() {
+            if !link.is_connect() {
+                continue;
+            }
+            let res = async {
+                match key {
+                    Some(key) => {
+                        call!(@run link=>tag as i32; &self.option.node_id, session_id, key)
+                    }
+                    None => call!(@run link=>tag as i32; &self.option.node_id, session_id),
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            if let Err(err) = res {
+                log::warn!("announce to cluster node {} error:{}", node_id, err);
+            }
+        }
+    }
+
+    /// Starts the task announcing created and removed sessions to the other nodes.
+    fn start_announce(wk: Weak<Cluster<C>>, mut events: Receiver<crate::SessionEvent>) {
+        tokio::spawn(async move {
+            loop {
+                let event = events.recv().await;
+                let cluster = match wk.upgrade() {
+                    Some(cluster) => cluster,
+                    None => break,
+                };
+                match event {
+                    Ok(event) => match event.kind {
+                        SessionEventKind::Created => {
+                            cluster
+                                .announce(ClusterTag::SessionUp, event.session_id, None)
+                                .await
+                        }
+                        SessionEventKind::Expired => {
+                            cluster
+                                .announce(ClusterTag::SessionDown, event.session_id, None)
+                                .await
+                        }
+                        _ => {}
+                    },
+                    Err(RecvError::Lagged(count)) => {
+                        log::warn!("cluster missed {} session events, sync all nodes", count);
+                        for node_id in cluster.links.keys() {
+                            if let Err(err) = cluster.sync_to(node_id).await {
+                                log::warn!("sync cluster node {} error:{}", node_id, err);
+                            }
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Starts the task connecting the links to the other nodes, a connected link syncs the directory.
+    fn start_check_links(wk: Weak<Cluster<C>>) {
+        tokio::spawn(async move {
+            while let Some(cluster) = wk.upgrade() {
+                for (node_id, link) in cluster.links.iter() {
+                    if !link.is_connect() {
+                        if let Err(err) = link.connect_network().await {
+                            log::debug!("connect cluster node {} error:{}", node_id, err);
+                        }
+                    }
+                }
+                drop(cluster);
+                sleep(LINK_CHECK_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Handles a command received from another node.
+    async fn execute(
+        &self,
+        cmd: i32,
+        mut dr: DataOwnedReader,
+    ) -> Result<(RetResult, Option<String>)> {
+        let args_len = dr.read_fixed::<u32>()?;
+        let mut result = RetResult::success();
+        let mut sync_node = None;
+        match cmd {
+            cmd if cmd == ClusterTag::Sync as i32 => {
+                ensure!(args_len == 3, "args len error");
+                let node_id = dr.pack_deserialize::<String>()?;
+                let sessions = dr.pack_deserialize::<Vec<i64>>()?;
+                let keys = dr.pack_deserialize::<Vec<(String, i64)>>()?;
+                log::info!("cluster node {} sync {} sessions", node_id, sessions.len());
+                self.directory
+                    .write()
+                    .unwrap()
+                    .sync(&node_id, sessions, keys);
+                sync_node = Some(node_id);
+            }
+            cmd if cmd == ClusterTag::SessionUp as i32 => {
+                ensure!(args_len == 2, "args len error");
+                let node_id = dr.pack_deserialize::<String>()?;
+                let session_id = dr.pack_deserialize::<i64>()?;
+                self.directory
+                    .write()
+                    .unwrap()
+                    .sessions
+                    .insert(session_id, node_id);
+            }
+            cmd if cmd == ClusterTag::SessionDown as i32 => {
+                ensure!(args_len == 2, "args len error");
+                let node_id = dr.pack_deserialize::<String>()?;
+                let session_id = dr.pack_deserialize::<i64>()?;
+                // a session moved to another node stays with its new owner
+                self.directory
+                    .write()
+                    .unwrap()
+                    .session_down(&node_id, session_id);
+            }
+            cmd if cmd == ClusterTag::KeyBound as i32 || cmd == ClusterTag::KeyUnbound as i32 => {
+                ensure!(args_len == 3, "args len error");
+                let node_id = dr.pack_deserialize::<String>()?;
+                let session_id = dr.pack_deserialize::<i64>()?;
+                let key = dr.pack_deserialize::<String>()?;
+                self.directory.write().unwrap().key_changed(
+                    &node_id,
+                    session_id,
+                    key,
+                    cmd == ClusterTag::KeyBound as i32,
+                );
+            }
+            cmd if cmd == ClusterTag::ForwardRun as i32 => {
+                ensure!(args_len == 2, "args len error");
+                let session_id = dr.pack_deserialize::<i64>()?;
+                let buff = dr.pack_deserialize::<Vec<u8>>()?;
+                let token = self
+                    .get_local_token(session_id)
+                    .await
+                    .ok_or(crate::error::Error::SessionNotFound(session_id))?;
+                token.run(Data::from(buff)).await?;
+            }
+            cmd if cmd == ClusterTag::ForwardCall as i32 => {
+                ensure!(args_len == 2, "args len error");
+                let session_id = dr.pack_deserialize::<i64>()?;
+                let buff = dr.pack_deserialize::<Vec<u8>>()?;
+                let token = self
+                    .get_local_token(session_id)
+                    .await
+                    .ok_or(crate::error::Error::SessionNotFound(session_id))?;
+                let ret = Self::call_local(&token, Data::from(buff)).await?;
+                let forward: ForwardResult = (
+                    ret.is_error,
+                    ret.error_id,
+                    ret.msg,
+                    ret.arguments
+                        .into_iter()
+                        .map(DataOwnedReader::into_inner)
+                        .collect(),
+                );
+                result.add_arg_buff(forward);
+            }
+            cmd if cmd == ClusterTag::ForwardBroadcast as i32 => {
+                ensure!(args_len == 3, "args len error");
+                let group = dr.pack_deserialize::<String>()?;
+                let buff = dr.pack_deserialize::<Vec<u8>>()?;
+                let except = dr.pack_deserialize::<Option<i64>>()?;
+                let manager = self.manager.upgrade().context("manager upgrade fail")?;
+                let failed = manager.broadcast(&group, Data::from(buff), except).await;
+                result.add_arg_buff(Self::failures_to_strings(failed));
+            }
+            cmd if cmd == ClusterTag::ForwardPublish as i32 => {
+                ensure!(args_len == 2, "args len error");
+                let topic = dr.pack_deserialize::<String>()?;
+                let buff = dr.pack_deserialize::<Vec<u8>>()?;
+                let manager = self.manager.upgrade().context("manager upgrade fail")?;
+                let failed = manager.publish(&topic, Data::from(buff)).await;
+                result.add_arg_buff(Self::failures_to_strings(failed));
+            }
+            _ => bail!("not found cluster cmd:{}", cmd),
+        }
+        Ok((result, sync_node))
+    }
+
+    /// Converts failures to strings to send them to another node.
+    #[inline]
+    fn failures_to_strings(failed: Vec<(i64, crate::error::Error)>) -> Vec<(i64, String)> {
+        failed
+            .into_iter()
+            .map(|(session_id, err)| (session_id, err.to_string()))
+            .collect()
+    }
+}
+
+/// Where a request for a session is sent.
+enum Route<'a, C: IController + 'static> {
+    /// The session is on this node.
+    Local(NetxToken<C>),
+    /// The session is on the node of this link.
+    Remote(&'a NetxClientArc<DefaultSessionStore>),
+}
+
+/// Creates the controllers of the cluster link server.
+pub struct ClusterCreateController<C: IController + 'static> {
+    cluster: Weak<Cluster<C>>,
+}
+
+impl<C: IController + 'static> ICreateController for ClusterCreateController<C> {
+    type Controller = ClusterController<C>;
+
+    #[inline]
+    fn create_controller(
+        &self,
+        _token: NetxToken<Self::Controller>,
+    ) -> Result<Arc<Self::Controller>> {
+        Ok(Arc::new(ClusterController {
+            cluster: self.cluster.clone(),
+            node_id: Mutex::new(None),
+        }))
+    }
+}
+
+/// Handles the link from another node to this node.
+pub struct ClusterController<C: IController + 'static> {
+    cluster: Weak<Cluster<C>>,
+    /// The node on the other end, known after its first sync.
+    node_id: Mutex<Option<String>>,
+}
+
+impl<C: IController + 'static> IController for ClusterController<C> {
+    async fn call(&self, _tt: u8, cmd_tag: i32, dr: DataOwnedReader) -> Result<RetResult> {
+        if cmd_tag == SpecialFunctionTag::Disconnect as i32 {
+            // the directory entries of a node are only kept while its link is up
+            let node_id = self.node_id.lock().unwrap().take();
+            if let (Some(node_id), Some(cluster)) = (node_id, self.cluster.upgrade()) {
+                log::info!("cluster node {} disconnect", node_id);
+                cluster.directory.write().unwrap().remove_node(&node_id);
+            }
+            return Ok(RetResult::success());
+        }
+        if cmd_tag == SpecialFunctionTag::Connect as i32
+            || cmd_tag == SpecialFunctionTag::Closed as i32
+        {
+            return Ok(RetResult::success());
+        }
+        let cluster = self.cluster.upgrade().context("cluster is drop")?;
+        let (result, sync_node) = cluster.execute(cmd_tag, dr).await?;
+        if sync_node.is_some() {
+            *self.node_id.lock().unwrap() = sync_node;
+        }
+        Ok(result)
+    }
+}
+
+/// Handles the link from this node to another node, syncing the directory when it connects.
+struct ClusterLinkController<C: IController + 'static> {
+    node_id: String,
+    cluster: Weak<Cluster<C>>,
+}
+
+#[async_trait::async_trait]
+impl<C: IController + 'static> netxclient::prelude::IController for ClusterLinkController<C> {
+    async fn call(
+        &self,
+        _tt: u8,
+        cmd_tag: i32,
+        _dr: DataOwnedReader,
+    ) -> Result<netxclient::prelude::RetResult> {
+        if cmd_tag == SpecialFunctionTag::Connect as i32 {
+            if let Some(cluster) = self.cluster.upgrade() {
+                let node_id = self.node_id.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = cluster.sync_to(&node_id).await {
+                        log::warn!("sync cluster node {} error:{}", node_id, err);
+                    }
+                });
+            }
+        }
+        Ok(netxclient::prelude::RetResult::success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BufMut;
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    const ECHO_ADD_ONE: i32 = 100;
+
+    struct TestController;
+
+    impl IController for TestController {
+        async fn call(&self, _tt: u8, _cmd_tag: i32, _dr: DataOwnedReader) -> Result<RetResult> {
+            Ok(RetResult::success())
+        }
+    }
+
+    struct TestCreateController;
+
+    impl ICreateController for TestCreateController {
+        type Controller = TestController;
+
+        fn create_controller(
+            &self,
+            _token: NetxToken<Self::Controller>,
+        ) -> Result<Arc<Self::Controller>> {
+            Ok(Arc::new(TestController))
+        }
+    }
+
+    /// The client of a session, answering `ECHO_ADD_ONE` with its argument plus one.
+    struct TestClientController;
+
+    #[async_trait::async_trait]
+    impl netxclient::prelude::IController for TestClientController {
+        async fn call(
+            &self,
+            _tt: u8,
+            cmd_tag: i32,
+            mut dr: DataOwnedReader,
+        ) -> Result<netxclient::prelude::RetResult> {
+            let mut result = netxclient::prelude::RetResult::success();
+            if cmd_tag == ECHO_ADD_ONE {
+                ensure!(dr.read_fixed::<u32>()? == 1, "args len error");
+                result.add_arg_buff(dr.pack_deserialize::<i32>()? + 1);
+            }
+            Ok(result)
+        }
+    }
+
+    fn free_addr() -> Result<String> {
+        Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.to_string())
+    }
+
+    fn cluster_option(node_id: &str, addr: &str, peer_id: &str, peer_addr: &str) -> ClusterOption {
+        let mut option = ClusterOption::new(node_id, addr, "cluster", "cluster_key");
+        option.peers.push(ClusterPeer {
+            node_id: peer_id.to_string(),
+            addr: peer_addr.to_string(),
+        });
+        option
+    }
+
+    /// Waits up to 10 seconds for a node to locate a session on the expected node.
+    async fn wait_locate<C: IController + 'static>(
+        cluster: &Cluster<C>,
+        session_id: i64,
+        expected: Option<&str>,
+    ) -> Result<()> {
+        let start = Instant::now();
+        while cluster.locate(session_id).await.as_deref() != expected {
+            ensure!(
+                start.elapsed() < Duration::from_secs(10),
+                "node {} locate {} timeout",
+                cluster.get_node_id(),
+                session_id
+            );
+            sleep(Duration::from_millis(50)).await;
+        }
+        Ok(())
+    }
+
+    /// Encodes a call like `call_peer!`, the serial is set by the node owning the session.
+    fn encode_call(cmd: i32, arg: i32) -> Result<Data> {
+        let mut data = Data::with_capacity(128);
+        data.write_fixed(0u32);
+        data.write_fixed(2400u32);
+        data.write_fixed(2u8);
+        data.write_fixed(cmd);
+        data.write_fixed(0i64);
+        data.write_fixed(1i32);
+        data.pack_serialize(arg)?;
+        let len = data.len();
+        (&mut data[0..4]).put_u32_le(len as u32);
+        Ok(data)
+    }
+
+    #[test]
+    fn directory_ignores_announces_of_a_former_owner() {
+        let mut directory = Directory::default();
+        directory.sync("a", vec![1], vec![("key".to_string(), 1)]);
+        // the session moves to node b before node a announces it down
+        directory.sessions.insert(1, "b".to_string());
+
+        directory.key_changed("a", 1, "key".to_string(), false);
+        directory.key_changed("a", 1, "other".to_string(), true);
+        directory.session_down("a", 1);
+        assert_eq!(directory.sessions.get(&1).map(String::as_str), Some("b"));
+        assert_eq!(directory.keys.get_names(1), vec!["key".to_string()]);
+
+        directory.key_changed("b", 1, "key".to_string(), false);
+        assert!(directory.keys.get_names(1).is_empty());
+        directory.session_down("b", 1);
+        assert!(directory.sessions.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn two_nodes_sync_route_forward_call_and_clean_on_link_drop() -> Result<()> {
+        let (server_addr, link_a, link_b) = (free_addr()?, free_addr()?, free_addr()?);
+        let server_a = NetXServer::new(
+            ServerOption::new(&server_addr, "test", "123"),
+            TestCreateController,
+        )
+        .await;
+        server_a.start().await?;
+        let server_b = NetXServer::new(
+            ServerOption::new(&free_addr()?, "test", "123"),
+            TestCreateController,
+        )
+        .await;
+        let node_a = Cluster::start(cluster_option("a", &link_a, "b", &link_b), &server_a).await?;
+        let node_b = Cluster::start(cluster_option("b", &link_b, "a", &link_a), &server_b).await?;
+
+        let client = NetXClient::new(
+            netxclient::prelude::ServerOption::new(
+                server_addr.clone(),
+                "test".to_string(),
+                "123".to_string(),
+                5000,
+            ),
+            DefaultSessionStore::default(),
+        );
+        client.init(TestClientController).await;
+        client.connect_network().await?;
+        let start = Instant::now();
+        while client.get_session_id() == 0 {
+            ensure!(
+                start.elapsed() < Duration::from_secs(10),
+                "session id timeout"
+            );
+            sleep(Duration::from_millis(50)).await;
+        }
+        let session_id = client.get_session_id();
+
+        // sync and route
+        wait_locate(&node_a, session_id, Some("a")).await?;
+        wait_locate(&node_b, session_id, Some("a")).await?;
+        assert!(matches!(
+            node_b.route(session_id).await,
+            Ok(Route::Remote(_))
+        ));
+        assert!(node_b.route(session_id + 1).await.is_err());
+
+        // forward call
+        let mut ret = node_b
+            .call(session_id, encode_call(ECHO_ADD_ONE, 41)?)
+            .await?
+            .check()?;
+        assert_eq!(ret.deserialize::<i32>()?, 42);
+
+        // the entries of a node are removed when its link drops
+        node_a.links["b"].close().await?;
+        wait_locate(&node_b, session_id, None).await?;
+        assert!(node_b.route(session_id).await.is_err());
+        Ok(())
+    }
+}
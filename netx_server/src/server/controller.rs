@@ -1,7 +1,10 @@
 use crate::async_token::NetxToken;
+use crate::option::DuplicateSessionPolicy;
+use crate::peer_certificate::PeerCertificate;
 use crate::result::RetResult;
 use anyhow::Result;
 use data_rw::DataOwnedReader;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 /// Trait representing a controller that can handle calls.
@@ -38,4 +41,70 @@ pub trait ICreateController {
         &self,
         token: NetxToken<Self::Controller>,
     ) -> Result<Arc<Self::Controller>>;
+
+    /// Maps the verified client certificate chain to an authenticated identity.
+    ///
+    /// The default implementation uses the subject of the leaf certificate.
+    ///
+    /// # Parameters
+    /// - `certificates`: The peer certificate chain, leaf first.
+    ///
+    /// # Returns
+    /// The identity of the peer, or `None` if the certificate is not accepted.
+    fn certificate_identity(&self, certificates: &[PeerCertificate]) -> Option<String> {
+        certificates.first().map(|cert| cert.subject.clone())
+    }
+
+    /// Decides whether a new connection is accepted.
+    ///
+    /// Called for every connection that passed the `ServerOption` admission rules,
+    /// before the TLS handshake. The default implementation accepts all connections.
+    ///
+    /// # Parameters
+    /// - `addr`: The address of the remote client, from the PROXY protocol header when enabled.
+    ///
+    /// # Returns
+    /// `true` to accept the connection, `false` to close it.
+    fn admit_connection(&self, addr: SocketAddr) -> bool {
+        let _ = addr;
+        true
+    }
+
+    /// Decides what happens when a client resumes a session that is still connected.
+    ///
+    /// A kicked connection is closed without the disconnect event, since the
+    /// session stays connected. The default implementation returns `policy`.
+    ///
+    /// # Parameters
+    /// - `session_id`: The session ID being resumed.
+    /// - `addr`: The address of the new connection.
+    /// - `policy`: The `ServerOption::duplicate_session_policy`.
+    ///
+    /// # Returns
+    /// The policy applied to this connection.
+    fn duplicate_session(
+        &self,
+        session_id: i64,
+        addr: SocketAddr,
+        policy: DuplicateSessionPolicy,
+    ) -> DuplicateSessionPolicy {
+        let _ = (session_id, addr);
+        policy
+    }
+
+    /// Decides whether a client may subscribe to a topic.
+    ///
+    /// Called when a client subscribes with `INetXClient::subscribe`, including the
+    /// subscriptions restored after a reconnect. The default implementation allows all topics.
+    ///
+    /// # Parameters
+    /// - `session_id`: The session ID of the client.
+    /// - `topic`: The topic name.
+    ///
+    /// # Returns
+    /// `true` to allow the subscription.
+    fn allow_subscribe(&self, session_id: i64, topic: &str) -> bool {
+        let _ = (session_id, topic);
+        true
+    }
 }
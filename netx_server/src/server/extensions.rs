@@ -0,0 +1,74 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+/// A map of values keyed by their type, one value per type.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Inserts a value, replacing the value of the same type.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to insert.
+    ///
+    /// # Returns
+    ///
+    /// The replaced value, if any.
+    #[inline]
+    pub fn insert<E: Any + Send + Sync>(&mut self, value: E) -> Option<E> {
+        self.map
+            .insert(TypeId::of::<E>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    /// Gets a reference to the value of a type.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if no value of this type was inserted.
+    #[inline]
+    pub fn get<E: Any + Send + Sync>(&self) -> Option<&E> {
+        self.map
+            .get(&TypeId::of::<E>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Gets a mutable reference to the value of a type.
+    ///
+    /// # Returns
+    ///
+    /// The value, or `None` if no value of this type was inserted.
+    #[inline]
+    pub fn get_mut<E: Any + Send + Sync>(&mut self) -> Option<&mut E> {
+        self.map
+            .get_mut(&TypeId::of::<E>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Removes the value of a type.
+    ///
+    /// # Returns
+    ///
+    /// The removed value, or `None` if no value of this type was inserted.
+    #[inline]
+    pub fn remove<E: Any + Send + Sync>(&mut self) -> Option<E> {
+        self.map
+            .remove(&TypeId::of::<E>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    /// Checks whether a value of a type was inserted.
+    #[inline]
+    pub fn contains<E: Any + Send + Sync>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<E>())
+    }
+
+    /// Removes all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
@@ -1,34 +1,40 @@
 use anyhow::{bail, Result};
 use bytes::BufMut;
-use data_rw::Data;
-use std::sync::{Arc, Weak};
+use data_rw::{Data, DataOwnedReader};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, ReadHalf};
+use tokio::time::Instant;
 
 #[cfg(all(feature = "tcpserver", not(feature = "tcp-channel-server")))]
 use tcpserver::{Builder, IPeer, ITCPServer, TCPPeer};
 
-use crate::async_token::{IAsyncToken, IAsyncTokenInner, NetxToken};
+use crate::async_token::{IAsyncToken, IAsyncTokenInner, NetxToken, SessionInfo, RESUME_TOKEN_LEN};
 use crate::async_token_manager::{IAsyncTokenManager, TokenManager};
 use crate::controller::ICreateController;
 use crate::owned_read_half_ex::ReadHalfExt;
+use crate::server::admission::{Admission, AdmissionGuard};
 use crate::server::async_token_manager::{
-    AsyncTokenManager, IAsyncTokenManagerCreateToken, ITokenManager,
+    AsyncTokenManager, IAsyncTokenManagerCreateToken, ITokenManager, Resume,
 };
+use crate::server::auth_guard::{AuthEvent, AuthGuard};
 use crate::server::maybe_stream::MaybeStream;
-use crate::{RetResult, ServerOption};
+use crate::server::proxy_protocol::read_proxy_header;
+use crate::server::session_event::SessionEvent;
+use crate::server::session_store::SessionStore;
+use crate::server::tls_config::{TlsConfig, TlsReloadHandle};
+use crate::{DuplicateSessionPolicy, PeerCertificate, RetResult, ServerOption};
 #[cfg(feature = "tcp-channel-server")]
 use tcp_channel_server::{Builder, ITCPServer, TCPPeer};
 
-cfg_if::cfg_if! {
-if #[cfg(feature = "use_openssl")]{
-   use openssl::ssl::{Ssl,SslAcceptor};
-   use tokio_openssl::SslStream;
-   use std::time::Duration;
-   use tokio::time::sleep;
-   use std::pin::Pin;
-}else if #[cfg(feature = "use_rustls")]{
-   use tokio_rustls::TlsAcceptor;
-}}
+#[cfg(feature = "use_openssl")]
+use openssl::ssl::SslAcceptor;
+#[cfg(feature = "use_rustls")]
+use tokio_rustls::TlsAcceptor;
 
 /// Type alias for `NetPeer` when the `tcpserver` feature is enabled and the `tcp-channel-server` feature is not enabled.
 #[cfg(all(feature = "tcpserver", not(feature = "tcp-channel-server")))]
@@ -46,12 +52,161 @@ pub(crate) enum SpecialFunctionTag {
     Connect = 2147483647,
     Disconnect = 2147483646,
     Closed = 2147483645,
+    Subscribe = 2147483644,
+    Unsubscribe = 2147483643,
+}
+
+/// Information gathered while accepting a stream, before the handshake.
+#[derive(Default)]
+struct AcceptedStream {
+    /// The verified TLS certificate chain, leaf first.
+    certificates: Vec<PeerCertificate>,
+    /// The original source address reported by the PROXY protocol header.
+    source_addr: Option<SocketAddr>,
+    /// The deadline of the handshake, started when the connection was accepted.
+    deadline: Option<Instant>,
+    /// The connection slot, released when the connection is closed.
+    _admission: Option<AdmissionGuard>,
+    /// Whether the handshake resumed an existing session.
+    resumed: bool,
 }
 
 /// Inner structure of `NetXServer` containing server options and async tokens.
 struct NetXServerInner<T: ICreateController + 'static> {
     option: ServerOption,
     async_tokens: TokenManager<T>,
+    /// The reloadable TLS configuration used to accept new connections.
+    tls_config: TlsReloadHandle,
+    /// The connection admission rules, an error if the options are invalid, returned by `start`.
+    admission: Result<Arc<Admission>>,
+    /// Failed handshake tracking and the audit event stream.
+    auth_guard: AuthGuard,
+    /// The number of connections dropped because the handshake timed out.
+    handshake_timeouts: AtomicU64,
+    /// Accepted streams, waiting for the handshake to pick them up.
+    accepted_streams: Mutex<HashMap<SocketAddr, AcceptedStream>>,
+}
+
+impl<T: ICreateController + 'static> NetXServerInner<T> {
+    /// Creates the shared server state and its token manager.
+    ///
+    /// # Arguments
+    ///
+    /// * `tls_config` - The TLS configuration used to accept new connections.
+    /// * `option` - The server options.
+    /// * `impl_controller` - The controller implementation.
+    ///
+    /// # Returns
+    ///
+    /// An `Arc` wrapped `NetXServerInner`.
+    #[inline]
+    fn new(
+        tls_config: TlsConfig,
+        option: ServerOption,
+        impl_controller: T,
+    ) -> Arc<NetXServerInner<T>> {
+        let async_tokens = AsyncTokenManager::new(
+            impl_controller,
+            option.request_out_time,
+            option.session_save_time,
+            option.outbox_capacity,
+            option.outbox_overflow,
+            option.replay_pending_calls,
+        );
+        Arc::new(NetXServerInner {
+            admission: Admission::new(&option),
+            auth_guard: AuthGuard::new(&option),
+            option,
+            async_tokens,
+            tls_config: TlsReloadHandle::new(tls_config),
+            accepted_streams: Default::default(),
+            handshake_timeouts: AtomicU64::new(0),
+        })
+    }
+
+    /// Gets the connection admission rules.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the rules, or an error if `allow_list` or `deny_list` is invalid.
+    #[inline]
+    fn get_admission(&self) -> Result<&Arc<Admission>> {
+        self.admission
+            .as_ref()
+            .map_err(|err| anyhow::anyhow!("invalid server option:{:#}", err))
+    }
+
+    /// Gets the deadline of a handshake starting now.
+    ///
+    /// # Returns
+    ///
+    /// The deadline, or `None` if `handshake_timeout` is `0`.
+    #[inline]
+    fn handshake_deadline(&self) -> Option<Instant> {
+        if self.option.handshake_timeout == 0 {
+            None
+        } else {
+            Some(Instant::now() + Duration::from_millis(self.option.handshake_timeout as u64))
+        }
+    }
+
+    /// Runs a handshake step, failing it if the handshake deadline passes.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - The handshake deadline, `None` waits forever.
+    /// * `future` - The handshake step.
+    ///
+    /// # Returns
+    ///
+    /// The result of the step, or an error if the deadline passed.
+    #[inline]
+    async fn until_deadline<R>(
+        &self,
+        deadline: Option<Instant>,
+        future: impl Future<Output = Result<R>>,
+    ) -> Result<R> {
+        match deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline, future).await {
+                Ok(res) => res,
+                Err(_) => {
+                    let count = self.handshake_timeouts.fetch_add(1, Ordering::Relaxed) + 1;
+                    log::warn!("handshake timeout, total handshake timeouts:{}", count);
+                    bail!("handshake timeout")
+                }
+            },
+            None => future.await,
+        }
+    }
+
+    /// Saves the information of an accepted stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the peer.
+    /// * `accepted` - The certificates, source address and connection slot of the stream.
+    #[inline]
+    fn save_accepted_stream(&self, addr: SocketAddr, accepted: AcceptedStream) {
+        self.accepted_streams.lock().unwrap().insert(addr, accepted);
+    }
+
+    /// Takes the information saved for the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address of the peer.
+    ///
+    /// # Returns
+    ///
+    /// The accepted stream information, empty if nothing was saved.
+    #[inline]
+    fn take_accepted_stream(&self, addr: &SocketAddr) -> AcceptedStream {
+        self.accepted_streams
+            .lock()
+            .unwrap()
+            .remove(addr)
+            .unwrap_or_default()
+    }
 }
 
 /// NetX Service structure.
@@ -70,140 +225,51 @@ impl<T> NetXServer<T>
 where
     T: ICreateController + 'static,
 {
-    cfg_if::cfg_if! {
-        if #[cfg(feature = "use_openssl")] {
-            /// Creates a new `NetXServer` instance with OpenSSL TLS encryption.
-            ///
-            /// # Arguments
-            ///
-            /// * `ssl_acceptor` - A reference to the `SslAcceptor` used for SSL/TLS connections.
-            /// * `option` - The server options.
-            /// * `impl_controller` - The controller implementation.
-            ///
-            /// # Returns
-            ///
-            /// A new instance of `NetXServer`.
-            ///
-            /// # Errors
-            ///
-            /// This function will return an error if the SSL/TLS stream initialization fails.
-            #[inline]
-            pub async fn new_ssl(
-                ssl_acceptor: &'static SslAcceptor,
-                option: ServerOption,
-                impl_controller: T,
-            ) -> NetXServer<T> {
-                let request_out_time = option.request_out_time;
-                let session_save_time = option.session_save_time;
-                let async_tokens =
-                    AsyncTokenManager::new(impl_controller, request_out_time, session_save_time);
-                let inner = Arc::new(NetXServerInner {
-                    option,
-                    async_tokens,
-                });
-                let serv = Builder::new(&inner.option.addr)
-                    .set_connect_event(|addr| {
-                        log::debug!("{} connect", addr);
-                        true
-                    })
-                    .set_stream_init(move |tcp_stream| async move {
-                        let ssl = Ssl::new(ssl_acceptor.context())?;
-                        let mut stream = SslStream::new(ssl, tcp_stream)?;
-                        sleep(Duration::from_millis(200)).await;
-                        Pin::new(&mut stream).accept().await?;
-                        Ok(MaybeStream::ServerSsl(stream))
-                    })
-                    .set_input_event(|mut reader, peer, inner| async move {
-                        let addr = peer.addr();
-                        let token = match Self::get_peer_token(&mut reader, &peer, &inner).await {
-                            Ok(token) => token,
-                            Err(er) => {
-                                log::debug!("user:{}:{},disconnect it", addr, er);
-                                return Ok(());
-                            }
-                        };
-                        token.set_peer(Some(peer)).await;
-                        let res=Self::read_buff_byline(&mut reader, &token).await;
-                        token.set_peer(None).await;
-                        token
-                            .call_special_function(SpecialFunctionTag::Disconnect as i32)
-                            .await?;
-                        inner
-                            .async_tokens
-                            .peer_disconnect(token.get_session_id())
-                            .await;
-                        res?;
-                        Ok(())
-                    })
-                    .build()
-                    .await;
-                NetXServer { inner, serv }
-            }
-        } else if #[cfg(feature = "use_rustls")] {
-            /// Creates a new `NetXServer` instance with Rustls TLS encryption.
-            ///
-            /// # Arguments
-            ///
-            /// * `acceptor` - A reference to the `TlsAcceptor` used for TLS connections.
-            /// * `option` - The server options.
-            /// * `impl_controller` - The controller implementation.
-            ///
-            /// # Returns
-            ///
-            /// A new instance of `NetXServer`.
-            ///
-            /// # Errors
-            ///
-            /// This function will return an error if the TLS stream initialization fails.
-            #[inline]
-            pub async fn new_tls(
-                acceptor:&'static TlsAcceptor,
-                option: ServerOption,
-                impl_controller: T,
-            ) -> NetXServer<T> {
-                let request_out_time = option.request_out_time;
-                let session_save_time = option.session_save_time;
-                let async_tokens =
-                    AsyncTokenManager::new(impl_controller, request_out_time, session_save_time);
-                let inner = Arc::new(NetXServerInner {
-                    option,
-                    async_tokens,
-                });
-                let serv = Builder::new(&inner.option.addr)
-                    .set_connect_event(|addr| {
-                        log::debug!("{} connect", addr);
-                        true
-                    })
-                    .set_stream_init(move |tcp_stream| async move {
-                       Ok(MaybeStream::ServerTls(acceptor.accept(tcp_stream).await?))
-                    })
-                    .set_input_event(|mut reader, peer, inner| async move {
-                        let addr = peer.addr();
-                        let token = match Self::get_peer_token(&mut reader, &peer, &inner).await {
-                            Ok(token) => token,
-                            Err(er) => {
-                                log::debug!("user:{}:{},disconnect it", addr, er);
-                                return Ok(());
-                            }
-                        };
-                        token.set_peer(Some(peer)).await;
-                        let res=Self::read_buff_byline(&mut reader,  &token).await;
-                        token.set_peer(None).await;
-                        token
-                            .call_special_function(SpecialFunctionTag::Disconnect as i32)
-                            .await?;
-                        inner
-                            .async_tokens
-                            .peer_disconnect(token.get_session_id())
-                            .await;
-                        res?;
-                        Ok(())
-                    })
-                    .build()
-                    .await;
-                NetXServer { inner, serv }
-            }
-        }
+    /// Creates a new `NetXServer` instance with OpenSSL TLS encryption.
+    ///
+    /// # Arguments
+    ///
+    /// * `ssl_acceptor` - A reference to the `SslAcceptor` used for SSL/TLS connections.
+    /// * `option` - The server options.
+    /// * `impl_controller` - The controller implementation.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `NetXServer`.
+    #[cfg(feature = "use_openssl")]
+    #[inline]
+    pub async fn new_ssl(
+        ssl_acceptor: &'static SslAcceptor,
+        option: ServerOption,
+        impl_controller: T,
+    ) -> NetXServer<T> {
+        Self::new_with_tls(
+            TlsConfig::OpenSsl(ssl_acceptor.clone()),
+            option,
+            impl_controller,
+        )
+        .await
+    }
+
+    /// Creates a new `NetXServer` instance with Rustls TLS encryption.
+    ///
+    /// # Arguments
+    ///
+    /// * `acceptor` - A reference to the `TlsAcceptor` used for TLS connections.
+    /// * `option` - The server options.
+    /// * `impl_controller` - The controller implementation.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `NetXServer`.
+    #[cfg(feature = "use_rustls")]
+    #[inline]
+    pub async fn new_tls(
+        acceptor: &'static TlsAcceptor,
+        option: ServerOption,
+        impl_controller: T,
+    ) -> NetXServer<T> {
+        Self::new_with_tls(TlsConfig::Rustls(acceptor.clone()), option, impl_controller).await
     }
 
     /// Creates a new `NetXServer` instance.
@@ -216,48 +282,122 @@ where
     /// # Returns
     ///
     /// A new instance of `NetXServer`.
+    #[inline]
+    pub async fn new(option: ServerOption, impl_controller: T) -> NetXServer<T> {
+        Self::new_with_tls(TlsConfig::None, option, impl_controller).await
+    }
+
+    /// Creates a new `NetXServer` instance with the given TLS configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `tls_config` - The TLS configuration, selecting plain TCP, OpenSSL or Rustls.
+    /// * `option` - The server options.
+    /// * `impl_controller` - The controller implementation.
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `NetXServer`.
     ///
     /// # Errors
     ///
-    /// This function will return an error if the server initialization fails.
+    /// Connections whose TLS handshake fails are dropped before the input event.
     #[inline]
-    pub async fn new(option: ServerOption, impl_controller: T) -> NetXServer<T> {
-        let request_out_time = option.request_out_time;
-        let session_save_time = option.session_save_time;
-        let async_tokens =
-            AsyncTokenManager::new(impl_controller, request_out_time, session_save_time);
-        let inner = Arc::new(NetXServerInner {
-            option,
-            async_tokens,
-        });
+    pub async fn new_with_tls(
+        tls_config: TlsConfig,
+        option: ServerOption,
+        impl_controller: T,
+    ) -> NetXServer<T> {
+        let inner = NetXServerInner::new(tls_config, option, impl_controller);
+        let stream_inner = inner.clone();
         let serv = Builder::new(&inner.option.addr)
             .set_connect_event(|addr| {
                 log::debug!("{} connect", addr);
                 true
             })
-            .set_stream_init(|tcp_stream| async move { Ok(MaybeStream::Plain(tcp_stream)) })
-            .set_input_event(|mut reader, peer, inner| async move {
-                let addr = peer.addr();
-                let token = match Self::get_peer_token(&mut reader, &peer, &inner).await {
-                    Ok(token) => token,
-                    Err(er) => {
-                        log::debug!("user:{}:{},disconnect it", addr, er);
-                        return Ok(());
-                    }
-                };
-                token.set_peer(Some(peer)).await;
-                let res = Self::read_buff_byline(&mut reader, &token).await;
-                token.set_peer(None).await;
-                token
-                    .call_special_function(SpecialFunctionTag::Disconnect as i32)
-                    .await?;
-                inner
-                    .async_tokens
-                    .peer_disconnect(token.get_session_id())
-                    .await;
-                res?;
-                Ok(())
+            .set_stream_init(move |mut tcp_stream| {
+                let inner = stream_inner.clone();
+                async move {
+                    let deadline = inner.handshake_deadline();
+                    let addr = tcp_stream.peer_addr()?;
+                    inner
+                        .until_deadline(deadline, async {
+                            let source_addr = if inner.option.proxy_protocol {
+                                read_proxy_header(&mut tcp_stream).await?
+                            } else {
+                                None
+                            };
+                            let remote_addr = source_addr.unwrap_or(addr);
+                            inner.auth_guard.check(remote_addr)?;
+                            let admission =
+                                inner.get_admission()?.admit(remote_addr.ip(), || unsafe {
+                                    inner
+                                        .async_tokens
+                                        .deref_inner()
+                                        .get_impl_controller()
+                                        .admit_connection(remote_addr)
+                                })?;
+                            let (stream, certificates) =
+                                inner.tls_config.accept(tcp_stream).await?;
+                            inner.save_accepted_stream(
+                                addr,
+                                AcceptedStream {
+                                    certificates,
+                                    source_addr,
+                                    deadline,
+                                    _admission: Some(admission),
+                                    resumed: false,
+                                },
+                            );
+                            Ok(stream)
+                        })
+                        .await
+                }
             })
+            .set_input_event(
+                |mut reader, peer, inner: Arc<NetXServerInner<T>>| async move {
+                    let addr = peer.addr();
+                    // keeps the connection slot until the input event returns
+                    let mut accepted = inner.take_accepted_stream(&addr);
+                    let deadline = accepted.deadline.or_else(|| inner.handshake_deadline());
+                    let token = match inner
+                        .until_deadline(
+                            deadline,
+                            Self::get_peer_token(&mut reader, &peer, &inner, &mut accepted),
+                        )
+                        .await
+                    {
+                        Ok(token) => token,
+                        Err(er) => {
+                            log::debug!("user:{}:{},disconnect it", addr, er);
+                            return Ok(());
+                        }
+                    };
+                    let remote_addr = accepted.source_addr.unwrap_or(addr);
+                    let kick_notify = token.set_peer(Some(peer.clone())).await;
+                    let kicked = kick_notify.notified();
+                    inner
+                        .async_tokens
+                        .peer_connect(token.get_session_id(), remote_addr, accepted.resumed)
+                        .await;
+                    let res = tokio::select! {
+                        res = Self::read_buff_byline(&mut reader, &token) => res,
+                        _ = kicked => Ok(()),
+                    };
+                    // a kicked or replaced connection leaves the session to the new one
+                    if token.remove_peer(&peer).await {
+                        token
+                            .call_special_function(SpecialFunctionTag::Disconnect as i32)
+                            .await?;
+                        inner
+                            .async_tokens
+                            .peer_disconnect(token.get_session_id(), remote_addr)
+                            .await;
+                    }
+                    res?;
+                    Ok(())
+                },
+            )
             .build()
             .await;
         NetXServer { inner, serv }
@@ -270,6 +410,7 @@ where
     /// * `reader` - A mutable reference to the `NetReadHalf` reader.
     /// * `peer` - An `Arc` reference to the `NetPeer`.
     /// * `inner` - An `Arc` reference to the `NetXServerInner` containing server options and async tokens.
+    /// * `accepted` - The information gathered while accepting the stream.
     ///
     /// # Returns
     ///
@@ -283,23 +424,46 @@ where
         mut reader: &mut NetReadHalf,
         peer: &Arc<NetPeer>,
         inner: &Arc<NetXServerInner<T>>,
+        accepted: &mut AcceptedStream,
     ) -> Result<NetxToken<T::Controller>> {
+        let certificates = std::mem::take(&mut accepted.certificates);
+        let source_addr = accepted.source_addr;
+        let remote_addr = source_addr.unwrap_or_else(|| peer.addr());
+        let identity = if certificates.is_empty() {
+            None
+        } else {
+            unsafe {
+                inner
+                    .async_tokens
+                    .deref_inner()
+                    .get_impl_controller()
+                    .certificate_identity(&certificates)
+            }
+        };
         let cmd = reader.read_i32_le().await?;
         if cmd != 1000 {
+            inner.auth_guard.failure(remote_addr, "not verify key");
             Self::send_to_key_verify_msg(peer, true, "not verify key").await?;
             bail!("not verify key")
         }
         let name = reader.read_string().await?;
         if !inner.option.service_name.is_empty() && name != inner.option.service_name {
+            inner.auth_guard.failure(remote_addr, "service name error");
             Self::send_to_key_verify_msg(peer, true, "service name error").await?;
             bail!("IP:{} service name:{} error", peer.addr(), name)
         }
         let password = reader.read_string().await?;
-        if !inner.option.verify_key.is_empty() && password != inner.option.verify_key {
+        let cert_verified = inner.option.client_cert_auth && identity.is_some();
+        if !cert_verified
+            && !inner.option.verify_key.is_empty()
+            && password != inner.option.verify_key
+        {
+            inner
+                .auth_guard
+                .failure(remote_addr, "service verify key error");
             Self::send_to_key_verify_msg(peer, true, "service verify key error").await?;
             bail!("IP:{} verify key:{} error", peer.addr(), name)
         }
-        Self::send_to_key_verify_msg(peer, false, "verify success").await?;
         let session = reader.read_i64_le().await?;
         let token = if session == 0 {
             inner
@@ -307,9 +471,25 @@ where
                 .create_token(Arc::downgrade(&inner.async_tokens))
                 .await?
         } else {
-            match inner.async_tokens.get_token(session).await {
-                Some(token) => token,
-                None => {
+            let resume_token = Self::read_resume_token(reader).await?;
+            match inner
+                .async_tokens
+                .resume_session(session, &resume_token, Arc::downgrade(&inner.async_tokens))
+                .await?
+            {
+                Resume::Resumed(token) => {
+                    Self::check_duplicate_session(peer, inner, &token, remote_addr).await?;
+                    accepted.resumed = true;
+                    token
+                }
+                res => {
+                    // a client without a stored resume token starts a new session,
+                    // only a token that does not match the session counts as a failure
+                    if matches!(res, Resume::Mismatch) && !resume_token.is_empty() {
+                        inner
+                            .auth_guard
+                            .failure(remote_addr, "session resume token error");
+                    }
                     inner
                         .async_tokens
                         .create_token(Arc::downgrade(&inner.async_tokens))
@@ -317,10 +497,94 @@ where
                 }
             }
         };
-
+        Self::send_to_key_verify_msg(peer, false, "verify success").await?;
+        inner
+            .auth_guard
+            .success(remote_addr, token.get_session_id(), identity.clone());
+        token.set_peer_certificates(certificates, identity).await;
+        token.set_source_addr(source_addr).await;
         Ok(token)
     }
 
+    /// Applies the duplicate session policy when the resumed session is still connected.
+    ///
+    /// # Arguments
+    ///
+    /// * `peer` - An `Arc` reference to the new `NetPeer`.
+    /// * `inner` - An `Arc` reference to the `NetXServerInner`.
+    /// * `token` - The resumed token.
+    /// * `remote_addr` - The address of the new connection.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the new connection is rejected.
+    #[inline]
+    async fn check_duplicate_session(
+        peer: &Arc<NetPeer>,
+        inner: &Arc<NetXServerInner<T>>,
+        token: &NetxToken<T::Controller>,
+        remote_addr: SocketAddr,
+    ) -> Result<()> {
+        if token.is_disconnect().await {
+            return Ok(());
+        }
+        let session_id = token.get_session_id();
+        let policy = unsafe {
+            inner
+                .async_tokens
+                .deref_inner()
+                .get_impl_controller()
+                .duplicate_session(
+                    session_id,
+                    remote_addr,
+                    inner.option.duplicate_session_policy,
+                )
+        };
+        match policy {
+            DuplicateSessionPolicy::KickOld => token.kick_peer(true).await,
+            DuplicateSessionPolicy::RejectNew => {
+                Self::send_to_key_verify_msg(peer, true, "session already connected").await?;
+                bail!(
+                    "IP:{} session id:{} already connected",
+                    remote_addr,
+                    session_id
+                )
+            }
+            DuplicateSessionPolicy::AllowBoth => {
+                log::debug!(
+                    "IP:{} session id:{} connected twice",
+                    remote_addr,
+                    session_id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the resume token sent with a non-zero session ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - A mutable reference to the `NetReadHalf` reader.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the resume token bytes.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the token is longer than a resume token.
+    #[inline]
+    async fn read_resume_token(reader: &mut NetReadHalf) -> Result<Vec<u8>> {
+        let len = reader.read_u32_le().await? as usize;
+        if len > RESUME_TOKEN_LEN {
+            bail!("resume token len:{} error", len)
+        }
+        let mut resume_token = vec![0; len];
+        reader.read_exact(&mut resume_token).await?;
+        Ok(resume_token)
+    }
+
     /// Reads data from the buffer line by line and processes it.
     ///
     /// # Arguments
@@ -359,6 +623,7 @@ where
         token: &NetxToken<T::Controller>,
     ) -> Result<()> {
         while let Ok(mut dr) = reader.read_buff().await {
+            unsafe { token.deref_inner().touch() };
             let cmd = dr.read_fixed::<i32>()?;
             match cmd {
                 2000 => {
@@ -408,6 +673,9 @@ where
                     let serial = dr.read_fixed::<i64>()?;
                     token.set_result(serial, dr).await?;
                 }
+                2600 => {
+                    Self::execute_batch(token, dr)?;
+                }
                 _ => {
                     log::error!("not found cmd:{}", cmd)
                 }
@@ -416,6 +684,70 @@ where
         Ok(())
     }
 
+    /// Dispatches the calls of a batch, replying with one batch result buffer.
+    ///
+    /// Each call runs like a single call, the batch result is sent
+    /// when every call of the batch waiting for a result is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - A reference to the `NetxToken`.
+    /// * `dr` - The batch, the number of calls followed by each call.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the batch could be read.
+    #[inline]
+    fn execute_batch(token: &NetxToken<T::Controller>, mut dr: DataOwnedReader) -> Result<()> {
+        let count = dr.read_fixed::<u32>()?;
+        let mut calls = Vec::new();
+        for _ in 0..count {
+            let mut entry = DataOwnedReader::new(dr.read_fixed_buf()?.to_vec());
+            let tt = entry.read_fixed::<u8>()?;
+            let cmd = entry.read_fixed::<i32>()?;
+            let serial = entry.read_fixed::<i64>()?;
+            let run_token = token.clone();
+            match tt {
+                0 => {
+                    tokio::spawn(async move {
+                        let _ = run_token.execute_controller(tt, cmd, entry).await;
+                    });
+                }
+                1 | 2 => {
+                    let call =
+                        tokio::spawn(
+                            async move { run_token.execute_controller(tt, cmd, entry).await },
+                        );
+                    calls.push((serial, call));
+                }
+                _ => {
+                    log::error!("not found call type:{}", tt)
+                }
+            }
+        }
+
+        if !calls.is_empty() {
+            let run_token = token.clone();
+            tokio::spawn(async move {
+                let mut results = Vec::with_capacity(calls.len());
+                for (serial, call) in calls {
+                    let res = match call.await {
+                        Ok(res) => res,
+                        Err(err) => RetResult::error(-1, format!("call {} error:{}", serial, err)),
+                    };
+                    results.push((serial, res));
+                }
+                if let Err(er) = run_token
+                    .send(Self::get_batch_result_buff(results).into_inner())
+                    .await
+                {
+                    log::error!("send batch buff error:{}", er);
+                }
+            });
+        }
+        Ok(())
+    }
+
     /// Constructs a result buffer from the given serial and result.
     ///
     /// # Arguments
@@ -432,6 +764,49 @@ where
 
         data.write_fixed(0u32);
         data.write_fixed(2500u32);
+        Self::write_result(&mut data, serial, result);
+
+        let len = data.len();
+        (&mut data[0..4]).put_u32_le(len as u32);
+        data
+    }
+
+    /// Constructs a batch result buffer from the results of the calls of a batch.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - The serial number and result of each call.
+    ///
+    /// # Returns
+    ///
+    /// A `Data` object containing each result, encoded like the body of a result buffer.
+    #[inline]
+    fn get_batch_result_buff(results: Vec<(i64, RetResult)>) -> Data {
+        let mut data = Data::with_capacity(1024);
+
+        data.write_fixed(0u32);
+        data.write_fixed(2700u32);
+        data.write_fixed(results.len() as u32);
+        for (serial, result) in results {
+            let mut entry = Data::with_capacity(256);
+            Self::write_result(&mut entry, serial, result);
+            data.write_fixed(&entry[..]);
+        }
+
+        let len = data.len();
+        (&mut data[0..4]).put_u32_le(len as u32);
+        data
+    }
+
+    /// Writes the serial and the result of a call.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The buffer to write to.
+    /// * `serial` - The serial number.
+    /// * `result` - The result of the call.
+    #[inline]
+    fn write_result(data: &mut Data, serial: i64, result: RetResult) {
         data.write_fixed(serial);
 
         if result.is_error {
@@ -445,13 +820,9 @@ where
                 data.write_fixed(argument.into_inner());
             }
         }
-
-        let len = data.len();
-        (&mut data[0..4]).put_u32_le(len as u32);
-        data
     }
 
-    /// Sends the session ID to the client.
+    /// Sends the session ID and the resume token to the client.
     ///
     /// # Arguments
     ///
@@ -467,6 +838,7 @@ where
         data.write_fixed(0u32);
         data.write_fixed(2000i32);
         data.write_fixed(session_id);
+        data.write_fixed(unsafe { token.deref_inner().get_resume_token() });
         let len = data.len();
         (&mut data[0..4]).put_u32_le(len as u32);
         token.send(data.into_inner()).await
@@ -510,13 +882,201 @@ where
         Arc::downgrade(&self.inner.async_tokens) as Weak<dyn ITokenManager<T::Controller>>
     }
 
+    /// Disconnects the client of a session, the session is kept and can be resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the session was found.
+    #[inline]
+    pub async fn kick_session(&self, session_id: i64) -> bool {
+        self.inner.async_tokens.kick(session_id).await
+    }
+
+    /// Disconnects the client of a session and removes the session immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the session was found.
+    #[inline]
+    pub async fn expire_session(&self, session_id: i64) -> bool {
+        self.inner.async_tokens.expire(session_id).await
+    }
+
+    /// Lists all sessions with their address, connect time and last activity.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` of `SessionInfo`.
+    #[inline]
+    pub async fn get_sessions(&self) -> Vec<SessionInfo> {
+        self.inner.async_tokens.get_sessions().await
+    }
+
+    /// Sends an encoded run call to the clients subscribed to a topic.
+    ///
+    /// Build the call with `call_peer!(@publish server=>topic, cmd; args)`, the subscribed
+    /// clients receive it as a call to `cmd` on their controller.
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - The topic name.
+    /// * `buff` - The encoded run call.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and error of each subscriber the call could not be sent to.
+    #[inline]
+    pub async fn publish(&self, topic: &str, buff: Data) -> Vec<(i64, crate::error::Error)> {
+        self.inner.async_tokens.publish(topic, buff).await
+    }
+
+    /// Sends an encoded call to many tokens at once and waits for all results with a shared deadline.
+    ///
+    /// Use `call_peer!(@call_all server=>tokens, timeout, cmd; args)` to encode the call
+    /// and deserialize the results.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - The tokens to call.
+    /// * `buff` - The encoded call.
+    /// * `timeout` - The time to wait for all results.
+    ///
+    /// # Returns
+    ///
+    /// A `Vec` containing the session ID and result of each token, in the order of `tokens`.
+    #[inline]
+    pub async fn call_all(
+        &self,
+        tokens: Vec<NetxToken<T::Controller>>,
+        buff: Data,
+        timeout: Duration,
+    ) -> Vec<(i64, crate::error::Result<RetResult>)> {
+        self.inner
+            .async_tokens
+            .call_all(tokens, buff, timeout)
+            .await
+    }
+
+    /// Sets the store used to persist sessions, so clients can resume them across restarts.
+    ///
+    /// Call it before `start`. Without it sessions are kept in memory only.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_store` - The session store, like a `FileSessionStore`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the stored sessions were restored.
+    #[inline]
+    pub async fn set_session_store(&self, session_store: Arc<dyn SessionStore>) -> Result<()> {
+        self.inner
+            .async_tokens
+            .set_session_store(session_store)
+            .await
+    }
+
+    /// Retrieves a handle for reloading the TLS configuration of this server.
+    ///
+    /// The handle can be kept by a signal handler or an admin API to rotate
+    /// certificates without restarting the server.
+    ///
+    /// # Returns
+    ///
+    /// A cloneable `TlsReloadHandle`.
+    #[inline]
+    pub fn get_tls_reload_handle(&self) -> TlsReloadHandle {
+        self.inner.tls_config.clone()
+    }
+
+    /// Replaces the TLS configuration used to accept new connections.
+    ///
+    /// Sessions that are already connected keep their current TLS stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `tls_config` - The new TLS configuration.
+    #[inline]
+    pub fn reload_tls(&self, tls_config: TlsConfig) {
+        self.inner.tls_config.reload(tls_config)
+    }
+
+    /// Gets the number of connections rejected by the admission rules.
+    ///
+    /// # Returns
+    ///
+    /// The rejected connection count since the server was created.
+    #[inline]
+    pub fn get_rejected_connections(&self) -> u64 {
+        self.inner
+            .get_admission()
+            .map(|admission| admission.get_rejected_count())
+            .unwrap_or(0)
+    }
+
+    /// Subscribes to the lifecycle events of all sessions.
+    ///
+    /// # Returns
+    ///
+    /// A broadcast receiver, events sent before subscribing are not received.
+    #[inline]
+    pub fn subscribe_session_events(&self) -> tokio::sync::broadcast::Receiver<SessionEvent> {
+        unsafe { self.inner.async_tokens.deref_inner().subscribe_events() }
+    }
+
+    /// Subscribes to the audit events of failed and successful handshakes.
+    ///
+    /// # Returns
+    ///
+    /// A broadcast receiver, events sent before subscribing are not received.
+    #[inline]
+    pub fn subscribe_auth_events(&self) -> tokio::sync::broadcast::Receiver<AuthEvent> {
+        self.inner.auth_guard.subscribe()
+    }
+
+    /// Gets the number of connections dropped because the handshake timed out.
+    ///
+    /// # Returns
+    ///
+    /// The handshake timeout count since the server was created.
+    #[inline]
+    pub fn get_handshake_timeouts(&self) -> u64 {
+        self.inner.handshake_timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of open connections.
+    ///
+    /// # Returns
+    ///
+    /// The number of admitted connections that are not closed yet.
+    #[inline]
+    pub fn get_connection_count(&self) -> usize {
+        self.inner
+            .get_admission()
+            .map(|admission| admission.get_connection_count())
+            .unwrap_or(0)
+    }
+
     /// Starts the server asynchronously.
     ///
     /// # Returns
     ///
     /// A `Result` containing a `JoinHandle` that resolves to a `Result`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `allow_list` or `deny_list` of the options is not a valid CIDR list.
     #[inline]
     pub async fn start(&self) -> crate::error::Result<tokio::task::JoinHandle<Result<()>>> {
+        self.inner.get_admission()?;
         Ok(self.serv.start(self.inner.clone()).await?)
     }
 
@@ -525,8 +1085,13 @@ where
     /// # Returns
     ///
     /// A `Result` indicating success or failure.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `allow_list` or `deny_list` of the options is not a valid CIDR list.
     #[inline]
     pub async fn start_block(&self) -> crate::error::Result<()> {
+        self.inner.get_admission()?;
         Ok(self.serv.start_block(self.inner.clone()).await?)
     }
 }
@@ -2,19 +2,20 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio::net::TcpStream;
-#[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+#[cfg(feature = "use_openssl")]
 use tokio_openssl::SslStream;
 
-#[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+#[cfg(feature = "use_rustls")]
 use tokio_rustls::server::TlsStream;
 
 /// Enum representing a stream that can be either plain TCP or TLS/SSL.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum MaybeStream {
     Plain(TcpStream),
-    #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+    #[cfg(feature = "use_openssl")]
     ServerSsl(SslStream<TcpStream>),
-    #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+    #[cfg(feature = "use_rustls")]
     ServerTls(TlsStream<TcpStream>),
 }
 
@@ -37,9 +38,9 @@ impl AsyncRead for MaybeStream {
     ) -> Poll<std::io::Result<()>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_read(cx, buf),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_read(cx, buf),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_read(cx, buf),
         }
     }
@@ -64,9 +65,9 @@ impl AsyncWrite for MaybeStream {
     ) -> Poll<Result<usize, std::io::Error>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_write(cx, buf),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_write(cx, buf),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_write(cx, buf),
         }
     }
@@ -84,9 +85,9 @@ impl AsyncWrite for MaybeStream {
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), std::io::Error>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_flush(cx),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_flush(cx),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_flush(cx),
         }
     }
@@ -107,9 +108,9 @@ impl AsyncWrite for MaybeStream {
     ) -> Poll<Result<(), std::io::Error>> {
         match self.get_mut() {
             MaybeStream::Plain(ref mut s) => Pin::new(s).poll_shutdown(cx),
-            #[cfg(all(feature = "use_openssl", not(feature = "use_rustls")))]
+            #[cfg(feature = "use_openssl")]
             MaybeStream::ServerSsl(ref mut s) => Pin::new(s).poll_shutdown(cx),
-            #[cfg(all(feature = "use_rustls", not(feature = "use_openssl")))]
+            #[cfg(feature = "use_rustls")]
             MaybeStream::ServerTls(ref mut s) => Pin::new(s).poll_shutdown(cx),
         }
     }
@@ -1,13 +1,38 @@
+mod admission;
 pub mod async_token;
 pub mod async_token_manager;
+pub mod auth_guard;
+mod bindings;
+#[cfg(feature = "cluster")]
+pub mod cluster;
 pub mod controller;
+pub mod extensions;
 pub mod impl_server;
 pub mod maybe_stream;
 pub mod option;
+pub mod outbox;
+pub mod peer_certificate;
+mod proxy_protocol;
 pub mod result;
+pub mod session_event;
+pub mod session_store;
+pub mod tls_config;
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+pub mod tls_config_builder;
 
 pub use async_token::*;
+pub use auth_guard::{AuthEvent, AuthEventKind};
+#[cfg(feature = "cluster")]
+pub use cluster::{Cluster, ClusterOption, ClusterPeer};
 pub use controller::*;
+pub use extensions::Extensions;
 pub use impl_server::*;
 pub use option::*;
+pub use outbox::OutboxStats;
+pub use peer_certificate::*;
 pub use result::*;
+pub use session_event::{SessionEvent, SessionEventKind};
+pub use session_store::{FileSessionStore, MemorySessionStore, SessionRecord, SessionStore};
+pub use tls_config::{TlsConfig, TlsReloadHandle};
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+pub use tls_config_builder::{ClientAuth, TlsConfigBuilder};
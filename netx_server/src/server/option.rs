@@ -13,6 +13,103 @@ pub struct ServerOption {
     pub request_out_time: u32,
     /// The time to save the session in milliseconds.
     pub session_save_time: u32,
+    /// Whether a verified TLS client certificate replaces the verification key.
+    ///
+    /// When enabled, a peer whose certificate maps to an identity through
+    /// `ICreateController::certificate_identity` does not need to send `verify_key`.
+    #[serde(default)]
+    pub client_cert_auth: bool,
+    /// Whether every connection starts with a PROXY protocol v1 or v2 header.
+    ///
+    /// Enable this only behind a load balancer that sends the header, the
+    /// original source address is then available from `IAsyncToken::get_remote_addr`.
+    #[serde(default)]
+    pub proxy_protocol: bool,
+    /// The maximum number of open connections, `0` means unlimited.
+    #[serde(default)]
+    pub max_connections: usize,
+    /// The maximum number of open connections from one IP address, `0` means unlimited.
+    #[serde(default)]
+    pub max_connections_per_ip: usize,
+    /// Only connections from these CIDRs (like `10.0.0.0/8`) are accepted, empty accepts all.
+    #[serde(default)]
+    pub allow_list: Vec<String>,
+    /// Connections from these CIDRs are rejected, even if they are in `allow_list`.
+    #[serde(default)]
+    pub deny_list: Vec<String>,
+    /// The time in milliseconds a connection has to finish the PROXY header, TLS accept
+    /// and the verification handshake before it is dropped, `0` means no limit.
+    #[serde(default = "default_handshake_timeout")]
+    pub handshake_timeout: u32,
+    /// The number of consecutive failed handshakes from one IP address before it is
+    /// locked out, `0` disables the lockout and is the default.
+    ///
+    /// Behind a load balancer all clients share its address, enable `proxy_protocol`
+    /// so failures are counted per client, or a few bad keys lock out everyone.
+    #[serde(default)]
+    pub auth_failure_threshold: u32,
+    /// The lockout time in milliseconds after reaching the threshold, doubled for every further failure.
+    #[serde(default = "default_auth_lockout_time")]
+    pub auth_lockout_time: u32,
+    /// The maximum lockout time in milliseconds.
+    #[serde(default = "default_auth_max_lockout_time")]
+    pub auth_max_lockout_time: u32,
+    /// What happens when a client resumes a session that is still connected.
+    ///
+    /// The controller can override it per connection with `ICreateController::duplicate_session`.
+    #[serde(default)]
+    pub duplicate_session_policy: DuplicateSessionPolicy,
+    /// The maximum number of pushes buffered per session while its client is disconnected,
+    /// `0` disables buffering and `IAsyncToken::run` fails with `TokenDisconnect`.
+    #[serde(default)]
+    pub outbox_capacity: usize,
+    /// What happens when the outbox of a disconnected session is full.
+    #[serde(default)]
+    pub outbox_overflow: OutboxOverflow,
+    /// Whether calls to a client still waiting for a result when it disconnects are sent
+    /// again when the session is resumed, instead of failing at once with `TokenDisconnect`.
+    ///
+    /// A replayed call may run twice on the client, the calls still fail after `request_out_time`.
+    #[serde(default)]
+    pub replay_pending_calls: bool,
+}
+
+/// What happens when the outbox of a disconnected session is full.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum OutboxOverflow {
+    /// Drop the oldest buffered message to make room.
+    #[default]
+    DropOldest,
+    /// Reject the new message, `IAsyncToken::run` fails with `OutboxFull`.
+    Reject,
+}
+
+/// What happens when a client resumes a session that is still connected.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DuplicateSessionPolicy {
+    /// Close the old connection, the new one takes over the session.
+    #[default]
+    KickOld,
+    /// Reject the new connection, the old one keeps the session.
+    RejectNew,
+    /// Keep both connections, messages to the client are sent on the newest one
+    /// and a kick closes only the newest one.
+    AllowBoth,
+}
+
+/// The default handshake timeout in milliseconds.
+fn default_handshake_timeout() -> u32 {
+    10000
+}
+
+/// The default lockout time in milliseconds.
+fn default_auth_lockout_time() -> u32 {
+    1000
+}
+
+/// The default maximum lockout time in milliseconds.
+fn default_auth_max_lockout_time() -> u32 {
+    300000
 }
 
 impl ServerOption {
@@ -35,6 +132,20 @@ impl ServerOption {
             verify_key: verify_key.to_string(),
             request_out_time: 5000,
             session_save_time: 5000,
+            client_cert_auth: false,
+            proxy_protocol: false,
+            max_connections: 0,
+            max_connections_per_ip: 0,
+            allow_list: Vec::new(),
+            deny_list: Vec::new(),
+            handshake_timeout: default_handshake_timeout(),
+            auth_failure_threshold: 0,
+            auth_lockout_time: default_auth_lockout_time(),
+            auth_max_lockout_time: default_auth_max_lockout_time(),
+            duplicate_session_policy: DuplicateSessionPolicy::KickOld,
+            outbox_capacity: 0,
+            outbox_overflow: OutboxOverflow::DropOldest,
+            replay_pending_calls: false,
         }
     }
 }
@@ -0,0 +1,149 @@
+use crate::OutboxOverflow;
+use std::collections::VecDeque;
+
+/// The counters of a session outbox.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OutboxStats {
+    /// The number of messages waiting for the client to reconnect.
+    pub queued: usize,
+    /// The number of messages buffered while the client was disconnected.
+    pub buffered: u64,
+    /// The number of buffered messages sent after the client reconnected.
+    pub flushed: u64,
+    /// The number of messages dropped or rejected because the outbox was full.
+    pub dropped: u64,
+}
+
+/// Buffers pushes to a disconnected client until the session is resumed.
+pub(crate) struct Outbox {
+    capacity: usize,
+    overflow: OutboxOverflow,
+    queue: VecDeque<Vec<u8>>,
+    stats: OutboxStats,
+}
+
+impl Outbox {
+    /// Creates an outbox.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of buffered messages, `0` disables buffering.
+    /// * `overflow` - What happens when the outbox is full.
+    #[inline]
+    pub(crate) fn new(capacity: usize, overflow: OutboxOverflow) -> Outbox {
+        Outbox {
+            capacity,
+            overflow,
+            queue: VecDeque::new(),
+            stats: OutboxStats::default(),
+        }
+    }
+
+    /// Checks whether buffering is enabled.
+    #[inline]
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Buffers a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `buff` - The encoded message.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the outbox is full and the message is rejected.
+    pub(crate) fn push(&mut self, buff: Vec<u8>) -> bool {
+        if self.queue.len() >= self.capacity {
+            self.stats.dropped += 1;
+            match self.overflow {
+                OutboxOverflow::DropOldest => {
+                    self.queue.pop_front();
+                }
+                OutboxOverflow::Reject => return false,
+            }
+        }
+        self.queue.push_back(buff);
+        self.stats.buffered += 1;
+        true
+    }
+
+    /// Takes all buffered messages, oldest first.
+    #[inline]
+    pub(crate) fn take(&mut self) -> VecDeque<Vec<u8>> {
+        std::mem::take(&mut self.queue)
+    }
+
+    /// Puts back messages that could not be sent, in front of the queue.
+    ///
+    /// # Arguments
+    ///
+    /// * `unsent` - The unsent messages, oldest first.
+    pub(crate) fn restore(&mut self, mut unsent: VecDeque<Vec<u8>>) {
+        unsent.append(&mut self.queue);
+        self.queue = unsent;
+    }
+
+    /// Records messages sent after the client reconnected.
+    #[inline]
+    pub(crate) fn flushed(&mut self, count: usize) {
+        self.stats.flushed += count as u64;
+    }
+
+    /// Gets the counters.
+    #[inline]
+    pub(crate) fn get_stats(&self) -> OutboxStats {
+        OutboxStats {
+            queued: self.queue.len(),
+            ..self.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(outbox: &mut Outbox, count: u8) -> Vec<bool> {
+        (0..count).map(|i| outbox.push(vec![i])).collect()
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_newest() {
+        let mut outbox = Outbox::new(2, OutboxOverflow::DropOldest);
+        assert!(outbox.is_enabled());
+        assert_eq!(fill(&mut outbox, 3), vec![true, true, true]);
+        assert_eq!(outbox.take(), vec![vec![1], vec![2]]);
+        let stats = outbox.get_stats();
+        assert_eq!((stats.queued, stats.buffered, stats.dropped), (0, 3, 1));
+    }
+
+    #[test]
+    fn reject_keeps_the_oldest() {
+        let mut outbox = Outbox::new(2, OutboxOverflow::Reject);
+        assert_eq!(fill(&mut outbox, 3), vec![true, true, false]);
+        let stats = outbox.get_stats();
+        assert_eq!((stats.queued, stats.buffered, stats.dropped), (2, 2, 1));
+        assert_eq!(outbox.take(), vec![vec![0], vec![1]]);
+    }
+
+    #[test]
+    fn restore_puts_unsent_messages_first() {
+        let mut outbox = Outbox::new(8, OutboxOverflow::Reject);
+        fill(&mut outbox, 3);
+        let mut sending = outbox.take();
+        sending.pop_front();
+        outbox.flushed(1);
+        outbox.push(vec![9]);
+        outbox.restore(sending);
+        assert_eq!(outbox.take(), vec![vec![1], vec![2], vec![9]]);
+        let stats = outbox.get_stats();
+        assert_eq!((stats.buffered, stats.flushed, stats.dropped), (4, 1, 0));
+    }
+
+    #[test]
+    fn zero_capacity_is_disabled() {
+        assert!(!Outbox::new(0, OutboxOverflow::DropOldest).is_enabled());
+    }
+}
@@ -0,0 +1,128 @@
+#[cfg(feature = "use_openssl")]
+use openssl::x509::X509Ref;
+
+/// Information about a certificate presented by the peer during the TLS handshake.
+#[derive(Clone, Debug)]
+pub struct PeerCertificate {
+    /// The subject distinguished name, like `CN=client, O=netx`.
+    pub subject: String,
+    /// The subject alternative names (DNS names, IP addresses, emails and URIs).
+    pub subject_alt_names: Vec<String>,
+    /// The SHA-256 fingerprint of the DER encoded certificate, as lowercase hex.
+    pub fingerprint: String,
+    /// The DER encoded certificate.
+    pub der: Vec<u8>,
+}
+
+impl PeerCertificate {
+    /// Creates a `PeerCertificate` from an OpenSSL certificate.
+    ///
+    /// # Arguments
+    ///
+    /// * `cert` - The certificate presented by the peer.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `PeerCertificate` or an error if the certificate cannot be encoded.
+    #[cfg(feature = "use_openssl")]
+    pub(crate) fn from_x509(cert: &X509Ref) -> anyhow::Result<PeerCertificate> {
+        use openssl::hash::MessageDigest;
+
+        let subject = cert
+            .subject_name()
+            .entries()
+            .map(|entry| {
+                let name = entry.object().nid().short_name().unwrap_or("UNKNOWN");
+                match entry.data().to_string() {
+                    Ok(value) => format!("{}={}", name, value),
+                    Err(_) => name.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut subject_alt_names = Vec::new();
+        if let Some(names) = cert.subject_alt_names() {
+            for name in names.iter() {
+                if let Some(dns) = name.dnsname() {
+                    subject_alt_names.push(dns.to_string());
+                } else if let Some(ip) = name.ipaddress() {
+                    subject_alt_names.push(ip_to_string(ip));
+                } else if let Some(email) = name.email() {
+                    subject_alt_names.push(email.to_string());
+                } else if let Some(uri) = name.uri() {
+                    subject_alt_names.push(uri.to_string());
+                }
+            }
+        }
+
+        Ok(PeerCertificate {
+            subject,
+            subject_alt_names,
+            fingerprint: to_hex(&cert.digest(MessageDigest::sha256())?),
+            der: cert.to_der()?,
+        })
+    }
+
+    /// Creates a `PeerCertificate` from a DER encoded certificate.
+    ///
+    /// # Arguments
+    ///
+    /// * `der` - The DER encoded certificate presented by the peer.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `PeerCertificate` or an error if the certificate cannot be parsed.
+    #[cfg(feature = "use_rustls")]
+    pub(crate) fn from_der(der: &[u8]) -> anyhow::Result<PeerCertificate> {
+        use sha2::{Digest, Sha256};
+        use x509_parser::extensions::GeneralName;
+
+        let (_, cert) = x509_parser::parse_x509_certificate(der)
+            .map_err(|err| anyhow::anyhow!("parse peer certificate error:{}", err))?;
+
+        let mut subject_alt_names = Vec::new();
+        if let Ok(Some(names)) = cert.subject_alternative_name() {
+            for name in names.value.general_names.iter() {
+                match name {
+                    GeneralName::DNSName(dns) => subject_alt_names.push(dns.to_string()),
+                    GeneralName::IPAddress(ip) => subject_alt_names.push(ip_to_string(ip)),
+                    GeneralName::RFC822Name(email) => subject_alt_names.push(email.to_string()),
+                    GeneralName::URI(uri) => subject_alt_names.push(uri.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(PeerCertificate {
+            subject: cert.subject().to_string(),
+            subject_alt_names,
+            fingerprint: to_hex(&Sha256::digest(der)),
+            der: der.to_vec(),
+        })
+    }
+}
+
+/// Formats raw IP address bytes from a subject alternative name.
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+fn ip_to_string(ip: &[u8]) -> String {
+    use std::convert::TryFrom;
+    use std::net::IpAddr;
+    match ip.len() {
+        4 => IpAddr::from(<[u8; 4]>::try_from(ip).unwrap()).to_string(),
+        16 => IpAddr::from(<[u8; 16]>::try_from(ip).unwrap()).to_string(),
+        _ => to_hex(ip),
+    }
+}
+
+/// Encodes bytes as lowercase hex.
+#[cfg(any(feature = "use_openssl", feature = "use_rustls"))]
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
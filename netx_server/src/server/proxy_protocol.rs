@@ -0,0 +1,258 @@
+use anyhow::{bail, ensure, Context, Result};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The PROXY protocol v2 signature.
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The maximum length of a PROXY protocol v1 header, including CRLF.
+const V1_MAX_LEN: usize = 107;
+
+/// The length of ` UNKNOWN\r\n`, the shortest PROXY protocol v1 header after `PROXY`.
+const V1_UNKNOWN_LEN: usize = 10;
+
+/// The length of ` TCP4 0.0.0.0 0.0.0.0 0 0\r\n`, the shortest `TCP4` header after `PROXY`.
+const V1_TCP4_MIN_LEN: usize = 27;
+
+/// Reads a PROXY protocol v1 or v2 header from the start of a connection.
+///
+/// Only the header is consumed, so the TLS handshake or the netx handshake
+/// can read the rest of the stream.
+///
+/// # Arguments
+///
+/// * `stream` - The accepted stream, before any other bytes are read.
+///
+/// # Returns
+///
+/// A `Result` containing the original source address, or `None` for
+/// `UNKNOWN` and `LOCAL` headers, like load balancer health checks.
+///
+/// # Errors
+///
+/// This function will return an error if the stream does not start with a valid header.
+pub(crate) async fn read_proxy_header<S: AsyncRead + Unpin>(
+    stream: &mut S,
+) -> Result<Option<SocketAddr>> {
+    let mut prefix = [0u8; 5];
+    stream.read_exact(&mut prefix).await?;
+    if &prefix == b"PROXY" {
+        read_v1(stream).await
+    } else if prefix[..] == V2_SIGNATURE[..5] {
+        let mut rest = [0u8; 7];
+        stream.read_exact(&mut rest).await?;
+        ensure!(
+            rest[..] == V2_SIGNATURE[5..],
+            "bad proxy protocol v2 signature"
+        );
+        read_v2(stream).await
+    } else {
+        bail!("not found proxy protocol header")
+    }
+}
+
+/// Reads the rest of a PROXY protocol v1 header, after `PROXY`.
+///
+/// The header is read in chunks no longer than the shortest header left,
+/// so no byte after the CRLF is consumed.
+async fn read_v1<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<SocketAddr>> {
+    let mut buff = [0u8; V1_MAX_LEN - 5];
+    // ` UNKNOWN\r\n` is the shortest header
+    let mut len = V1_UNKNOWN_LEN;
+    stream.read_exact(&mut buff[..len]).await?;
+    if buff.starts_with(b" TCP4 ") {
+        stream.read_exact(&mut buff[len..V1_TCP4_MIN_LEN]).await?;
+        len = V1_TCP4_MIN_LEN;
+    }
+    while !buff[..len].ends_with(b"\r\n") {
+        let need = if buff[len - 1] == b'\r' { 1 } else { 2 };
+        ensure!(
+            len + need <= buff.len(),
+            "proxy protocol v1 header too long"
+        );
+        stream.read_exact(&mut buff[len..len + need]).await?;
+        len += need;
+    }
+    let line = std::str::from_utf8(&buff[..len - 2])?;
+    let mut parts = line.split(' ').skip(1);
+    match parts.next() {
+        Some(protocol @ ("TCP4" | "TCP6")) => {
+            let mut next_ip = |name| -> Result<IpAddr> {
+                let ip = parts
+                    .next()
+                    .with_context(|| format!("proxy protocol v1 missing {} address", name))?;
+                Ok(if protocol == "TCP4" {
+                    IpAddr::V4(ip.parse::<Ipv4Addr>()?)
+                } else {
+                    IpAddr::V6(ip.parse::<Ipv6Addr>()?)
+                })
+            };
+            let ip = next_ip("source")?;
+            let _destination = next_ip("destination")?;
+            let port = parts
+                .next()
+                .context("proxy protocol v1 missing source port")?
+                .parse::<u16>()?;
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        Some("UNKNOWN") => Ok(None),
+        _ => bail!("bad proxy protocol v1 header:{}", line),
+    }
+}
+
+/// Reads the rest of a PROXY protocol v2 header, after the signature.
+async fn read_v2<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<SocketAddr>> {
+    let version_command = stream.read_u8().await?;
+    let family = stream.read_u8().await?;
+    let len = stream.read_u16().await? as usize;
+    let mut addresses = vec![0u8; len];
+    stream.read_exact(&mut addresses).await?;
+
+    ensure!(
+        version_command >> 4 == 2,
+        "bad proxy protocol version:{}",
+        version_command >> 4
+    );
+    match version_command & 0x0F {
+        // LOCAL
+        0 => return Ok(None),
+        // PROXY
+        1 => {}
+        command => bail!("bad proxy protocol v2 command:{}", command),
+    }
+
+    match family {
+        // TCP over IPv4
+        0x11 => {
+            ensure!(len >= 12, "proxy protocol v2 ipv4 address too short");
+            let ip = Ipv4Addr::from(<[u8; 4]>::try_from(&addresses[0..4])?);
+            let port = u16::from_be_bytes([addresses[8], addresses[9]]);
+            Ok(Some(SocketAddr::new(IpAddr::V4(ip), port)))
+        }
+        // TCP over IPv6
+        0x21 => {
+            ensure!(len >= 36, "proxy protocol v2 ipv6 address too short");
+            let ip = Ipv6Addr::from(<[u8; 16]>::try_from(&addresses[0..16])?);
+            let port = u16::from_be_bytes([addresses[32], addresses[33]]);
+            Ok(Some(SocketAddr::new(IpAddr::V6(ip), port)))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_header(version_command: u8, family: u8, addresses: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(version_command);
+        header.push(family);
+        header.extend_from_slice(&(addresses.len() as u16).to_be_bytes());
+        header.extend_from_slice(addresses);
+        header
+    }
+
+    #[tokio::test]
+    async fn v1_reads_source_and_leaves_the_rest() -> Result<()> {
+        let mut stream = &b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\nnetx"[..];
+        let addr = read_proxy_header(&mut stream).await?;
+        assert_eq!(addr, Some("192.168.0.1:56324".parse()?));
+        assert_eq!(stream, b"netx");
+
+        let mut stream = &b"PROXY TCP6 2001:db8::1 2001:db8::2 4000 443\r\n"[..];
+        let addr = read_proxy_header(&mut stream).await?;
+        assert_eq!(addr, Some("[2001:db8::1]:4000".parse()?));
+
+        let mut stream = &b"PROXY TCP4 0.0.0.0 0.0.0.0 0 0\r\nnetx"[..];
+        let addr = read_proxy_header(&mut stream).await?;
+        assert_eq!(addr, Some("0.0.0.0:0".parse()?));
+        assert_eq!(stream, b"netx");
+
+        let mut stream = &b"PROXY UNKNOWN\r\nnetx"[..];
+        assert_eq!(read_proxy_header(&mut stream).await?, None);
+        assert_eq!(stream, b"netx");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn v1_rejects_malformed_and_truncated_headers() {
+        let mut too_long = b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443 ".to_vec();
+        too_long.extend_from_slice(&[b'0'; V1_MAX_LEN]);
+        too_long.extend_from_slice(b"\r\n");
+        let headers = [
+            b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443".to_vec(),
+            b"PROXY TCP4 192.168.0.1\r\n".to_vec(),
+            b"PROXY TCP4 not_ip 10.0.0.1 56324 443\r\n".to_vec(),
+            b"PROXY TCP4 192.168.0.1 10.0.0.1 99999 443\r\n".to_vec(),
+            b"PROXY UDP4 192.168.0.1 10.0.0.1 56324 443\r\n".to_vec(),
+            b"PROXY TCP4 2001:db8::1 10.0.0.1 56324 443\r\n".to_vec(),
+            b"PROXY TCP4 192.168.0.1 2001:db8::2 56324 443\r\n".to_vec(),
+            b"PROXY TCP6 192.168.0.1 2001:db8::2 4000 443\r\n".to_vec(),
+            b"PRO".to_vec(),
+            too_long,
+        ];
+        for header in headers {
+            let mut stream = &header[..];
+            assert!(read_proxy_header(&mut stream).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn v2_reads_source_and_leaves_the_rest() -> Result<()> {
+        let mut header = v2_header(
+            0x21,
+            0x11,
+            &[192, 168, 0, 1, 10, 0, 0, 1, 0xDC, 0x04, 0x01, 0xBB],
+        );
+        header.extend_from_slice(b"netx");
+        let mut stream = &header[..];
+        let addr = read_proxy_header(&mut stream).await?;
+        assert_eq!(addr, Some("192.168.0.1:56324".parse()?));
+        assert_eq!(stream, b"netx");
+
+        let source: Ipv6Addr = "2001:db8::1".parse()?;
+        let mut addresses = source.octets().to_vec();
+        addresses.extend_from_slice(&[0; 16]);
+        addresses.extend_from_slice(&[0x0F, 0xA0, 0x01, 0xBB]);
+        let header = v2_header(0x21, 0x21, &addresses);
+        let mut stream = &header[..];
+        let addr = read_proxy_header(&mut stream).await?;
+        assert_eq!(addr, Some("[2001:db8::1]:4000".parse()?));
+
+        // LOCAL, like a health check of the load balancer
+        let header = v2_header(0x20, 0x00, &[]);
+        let mut stream = &header[..];
+        assert_eq!(read_proxy_header(&mut stream).await?, None);
+
+        // unix sockets have no source address
+        let header = v2_header(0x21, 0x31, &[0; 216]);
+        let mut stream = &header[..];
+        assert_eq!(read_proxy_header(&mut stream).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn v2_rejects_malformed_and_truncated_headers() {
+        let ipv4 = [192, 168, 0, 1, 10, 0, 0, 1, 0xDC, 0x04, 0x01, 0xBB];
+        let mut bad_signature = v2_header(0x21, 0x11, &ipv4);
+        bad_signature[6] = b'X';
+        let mut truncated = v2_header(0x21, 0x11, &ipv4);
+        truncated.truncate(truncated.len() - 1);
+        let headers = [
+            bad_signature,
+            truncated,
+            V2_SIGNATURE[..8].to_vec(),
+            v2_header(0x11, 0x11, &ipv4),
+            v2_header(0x22, 0x11, &ipv4),
+            v2_header(0x21, 0x11, &ipv4[..8]),
+            v2_header(0x21, 0x21, &ipv4),
+            b"GET / HTTP/1.1\r\n".to_vec(),
+        ];
+        for header in headers {
+            let mut stream = &header[..];
+            assert!(read_proxy_header(&mut stream).await.is_err());
+        }
+    }
+}
@@ -175,6 +175,7 @@ impl RetResult {
     ///
     /// A `Result` containing a mutable reference to the argument or an error.
     #[inline]
+    #[allow(clippy::io_other_error)]
     pub fn get(&mut self, index: usize) -> io::Result<&mut DataOwnedReader> {
         if index >= self.len() {
             return Err(io::Error::new(ErrorKind::Other, "index >= len"));
@@ -192,6 +193,7 @@ impl RetResult {
     ///
     /// A `Result` containing the deserialized value or an error.
     #[inline]
+    #[allow(clippy::io_other_error)]
     pub fn deserialize<'a, T: Deserialize<'a> + 'static>(&'a mut self) -> crate::error::Result<T> {
         if self.is_empty() {
             return Err(io::Error::new(ErrorKind::Other, "index >= len").into());
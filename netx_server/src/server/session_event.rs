@@ -0,0 +1,33 @@
+use std::net::SocketAddr;
+use std::time::SystemTime;
+
+/// The capacity of the session event channel, slow subscribers lose the oldest events.
+pub(crate) const SESSION_EVENT_CAPACITY: usize = 1024;
+
+/// A lifecycle event of a session.
+#[derive(Clone, Debug)]
+pub struct SessionEvent {
+    /// The session ID.
+    pub session_id: i64,
+    /// The address of the remote client, `None` for events without a connection.
+    pub addr: Option<SocketAddr>,
+    /// The time of the event.
+    pub time: SystemTime,
+    /// What happened.
+    pub kind: SessionEventKind,
+}
+
+/// The kind of a `SessionEvent`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SessionEventKind {
+    /// A new session was created.
+    Created,
+    /// A client connected to a new session.
+    Connected,
+    /// The connection of a session was closed, the session is kept for `session_save_time`.
+    Disconnected,
+    /// A client reconnected to an existing session.
+    Resumed,
+    /// The session was removed.
+    Expired,
+}
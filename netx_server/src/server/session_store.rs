@@ -0,0 +1,432 @@
+use anyhow::{ensure, Result};
+use data_rw::{Data, DataOwnedReader};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// The persisted part of a session, enough to resume it after a server restart.
+#[derive(Clone, Debug)]
+pub struct SessionRecord {
+    /// The session ID.
+    pub session_id: i64,
+    /// The secret a client must present with the session ID to resume the session.
+    pub resume_token: Vec<u8>,
+    /// When the session expires, `None` while a client is connected.
+    pub expires_at: Option<SystemTime>,
+    /// The application state of the session, set with `IAsyncToken::set_session_state`.
+    pub state: Vec<u8>,
+}
+
+impl SessionRecord {
+    /// Checks whether the session has expired.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `expires_at` has passed.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= SystemTime::now())
+    }
+
+    /// Encodes the record to bytes.
+    ///
+    /// # Returns
+    ///
+    /// The encoded record.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Data::with_capacity(64 + self.state.len());
+        data.write_fixed(self.session_id);
+        data.write_fixed(self.resume_token.as_slice());
+        data.write_fixed(
+            self.expires_at
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|time| time.as_millis() as u64)
+                .unwrap_or(0),
+        );
+        data.write_fixed(self.state.as_slice());
+        data.into_inner()
+    }
+
+    /// Decodes a record encoded by `to_bytes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `buff` - The encoded record.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the decoded record.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the bytes are not a valid record.
+    pub fn from_bytes(buff: Vec<u8>) -> Result<SessionRecord> {
+        let mut dr = DataOwnedReader::new(buff);
+        let session_id = dr.read_fixed::<i64>()?;
+        let resume_token = dr.read_fixed_buf()?.to_vec();
+        let expires_at = match dr.read_fixed::<u64>()? {
+            0 => None,
+            millis => Some(UNIX_EPOCH + Duration::from_millis(millis)),
+        };
+        let state = dr.read_fixed_buf()?.to_vec();
+        Ok(SessionRecord {
+            session_id,
+            resume_token,
+            expires_at,
+            state,
+        })
+    }
+}
+
+/// Persists sessions so clients can resume them across server restarts.
+///
+/// The token manager saves a record when a session is created, connects,
+/// disconnects or changes its state, and removes it when the session expires.
+/// The calls run in order on a task of their own, a slow store does not hold the other sessions.
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Prepares the stored sessions when the store is set on the server.
+    ///
+    /// Sessions still connected when the server stopped get `session_save_time`
+    /// to be resumed, expired sessions are removed. The default implementation does nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_save_time` - The time a disconnected session is kept.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the ID and `expires_at` of each kept session, they are removed
+    /// if no client resumes them before `expires_at`, or within `session_save_time` if it is `None`.
+    async fn restore(&self, session_save_time: Duration) -> Result<Vec<(i64, Option<SystemTime>)>> {
+        let _ = session_save_time;
+        Ok(Vec::new())
+    }
+
+    /// Saves a session, replacing the previous record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The session record.
+    async fn save(&self, record: SessionRecord) -> Result<()>;
+
+    /// Loads a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the record, or `None` if the session is not stored.
+    async fn load(&self, session_id: i64) -> Result<Option<SessionRecord>>;
+
+    /// Removes a session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    async fn remove(&self, session_id: i64) -> Result<()>;
+}
+
+/// A `SessionStore` in memory, sessions are lost when the server stops.
+#[derive(Default)]
+pub struct MemorySessionStore {
+    records: Mutex<HashMap<i64, SessionRecord>>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for MemorySessionStore {
+    #[inline]
+    async fn save(&self, record: SessionRecord) -> Result<()> {
+        self.records
+            .lock()
+            .unwrap()
+            .insert(record.session_id, record);
+        Ok(())
+    }
+
+    #[inline]
+    async fn load(&self, session_id: i64) -> Result<Option<SessionRecord>> {
+        Ok(self.records.lock().unwrap().get(&session_id).cloned())
+    }
+
+    #[inline]
+    async fn remove(&self, session_id: i64) -> Result<()> {
+        self.records.lock().unwrap().remove(&session_id);
+        Ok(())
+    }
+}
+
+/// A `SessionStore` keeping one file per session in a directory.
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    /// The extension of session files.
+    const EXTENSION: &'static str = "session";
+
+    /// Opens the store, creating the directory if it does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory of the session files.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the store.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the directory cannot be created.
+    pub fn open(dir: impl AsRef<Path>) -> Result<FileSessionStore> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(FileSessionStore {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// Gets the file of a session.
+    #[inline]
+    fn path(&self, session_id: i64) -> PathBuf {
+        self.dir.join(format!("{}.{}", session_id, Self::EXTENSION))
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for FileSessionStore {
+    async fn restore(&self, session_save_time: Duration) -> Result<Vec<(i64, Option<SystemTime>)>> {
+        let mut kept = Vec::new();
+        let mut dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(Self::EXTENSION) {
+                continue;
+            }
+            let mut record = match SessionRecord::from_bytes(tokio::fs::read(&path).await?) {
+                Ok(record) => record,
+                Err(err) => {
+                    log::warn!("remove bad session file:{} error:{}", path.display(), err);
+                    tokio::fs::remove_file(&path).await?;
+                    continue;
+                }
+            };
+            if record.is_expired() {
+                tokio::fs::remove_file(&path).await?;
+                continue;
+            }
+            if record.expires_at.is_none() {
+                record.expires_at = Some(SystemTime::now() + session_save_time);
+                self.save(record.clone()).await?;
+            }
+            kept.push((record.session_id, record.expires_at));
+        }
+        Ok(kept)
+    }
+
+    async fn save(&self, record: SessionRecord) -> Result<()> {
+        let path = self.path(record.session_id);
+        let temp = path.with_extension("tmp");
+        tokio::fs::write(&temp, record.to_bytes()).await?;
+        tokio::fs::rename(&temp, &path).await?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: i64) -> Result<Option<SessionRecord>> {
+        match tokio::fs::read(self.path(session_id)).await {
+            Ok(buff) => {
+                let record = SessionRecord::from_bytes(buff)?;
+                ensure!(
+                    record.session_id == session_id,
+                    "session file:{} id mismatch",
+                    session_id
+                );
+                Ok(Some(record))
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn remove(&self, session_id: i64) -> Result<()> {
+        match tokio::fs::remove_file(self.path(session_id)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A call of the token manager to the `SessionStore`.
+enum SessionStoreOp {
+    Save(SessionRecord),
+    Remove(i64),
+    Load(i64, oneshot::Sender<Result<Option<SessionRecord>>>),
+}
+
+/// Runs the calls to a `SessionStore` in order on a task of their own,
+/// so the token manager does not wait for the store to save, remove or load a session.
+#[derive(Clone)]
+pub(crate) struct SessionStoreQueue {
+    tx: UnboundedSender<SessionStoreOp>,
+}
+
+impl SessionStoreQueue {
+    /// Starts the task running the calls to the store, it stops when every clone of the queue is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The session store.
+    ///
+    /// # Returns
+    ///
+    /// The queue of the store.
+    pub(crate) fn new(store: Arc<dyn SessionStore>) -> SessionStoreQueue {
+        let (tx, mut rx) = unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(op) = rx.recv().await {
+                match op {
+                    SessionStoreOp::Save(record) => {
+                        let session_id = record.session_id;
+                        if let Err(er) = store.save(record).await {
+                            log::error!("save session {} to store err:{}", session_id, er)
+                        }
+                    }
+                    SessionStoreOp::Remove(session_id) => {
+                        if let Err(er) = store.remove(session_id).await {
+                            log::error!("remove session {} from store err:{}", session_id, er)
+                        }
+                    }
+                    SessionStoreOp::Load(session_id, tx) => {
+                        let _ = tx.send(store.load(session_id).await);
+                    }
+                }
+            }
+        });
+        SessionStoreQueue { tx }
+    }
+
+    /// Queues saving a session.
+    #[inline]
+    pub(crate) fn save(&self, record: SessionRecord) {
+        let _ = self.tx.send(SessionStoreOp::Save(record));
+    }
+
+    /// Queues removing a session.
+    #[inline]
+    pub(crate) fn remove(&self, session_id: i64) {
+        let _ = self.tx.send(SessionStoreOp::Remove(session_id));
+    }
+
+    /// Loads a session after the queued saves and removes are done.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - The session ID.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the record, or `None` if the session is not stored.
+    pub(crate) async fn load(&self, session_id: i64) -> Result<Option<SessionRecord>> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(SessionStoreOp::Load(session_id, tx))
+            .map_err(|_| anyhow::anyhow!("session store is closed"))?;
+        rx.await
+            .map_err(|_| anyhow::anyhow!("session store is closed"))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(session_id: i64, expires_at: Option<SystemTime>) -> SessionRecord {
+        SessionRecord {
+            session_id,
+            resume_token: vec![1, 2, 3, 4],
+            expires_at,
+            state: b"state".to_vec(),
+        }
+    }
+
+    #[test]
+    fn record_round_trip() -> Result<()> {
+        let expires_at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        for record in [
+            record(1, None),
+            record(-2, Some(expires_at)),
+            SessionRecord {
+                session_id: i64::MAX,
+                resume_token: Vec::new(),
+                expires_at: None,
+                state: Vec::new(),
+            },
+        ] {
+            let decoded = SessionRecord::from_bytes(record.to_bytes())?;
+            assert_eq!(decoded.session_id, record.session_id);
+            assert_eq!(decoded.resume_token, record.resume_token);
+            assert_eq!(decoded.expires_at, record.expires_at);
+            assert_eq!(decoded.state, record.state);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn record_from_truncated_bytes_fails() {
+        let buff = record(1, Some(SystemTime::now())).to_bytes();
+        for len in 0..buff.len() {
+            assert!(
+                SessionRecord::from_bytes(buff[..len].to_vec()).is_err(),
+                "len:{}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn record_is_expired() {
+        assert!(!record(1, None).is_expired());
+        assert!(record(1, Some(SystemTime::now() - Duration::from_secs(1))).is_expired());
+        assert!(!record(1, Some(SystemTime::now() + Duration::from_secs(60))).is_expired());
+    }
+
+    #[tokio::test]
+    async fn file_store_restore_keeps_only_live_sessions() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("netx_session_store_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = FileSessionStore::open(&dir)?;
+        store.save(record(1, None)).await?;
+        store
+            .save(record(2, Some(SystemTime::now() + Duration::from_secs(60))))
+            .await?;
+        store
+            .save(record(3, Some(SystemTime::now() - Duration::from_secs(1))))
+            .await?;
+        std::fs::write(store.path(4), b"bad")?;
+
+        let mut kept = store.restore(Duration::from_secs(30)).await?;
+        kept.sort();
+        assert_eq!(
+            kept.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(kept.iter().all(|(_, expires_at)| expires_at.is_some()));
+        // a session connected when the server stopped gets session_save_time to be resumed
+        let restored = store.load(1).await?.expect("session 1 not found");
+        assert!(restored.expires_at.is_some());
+        assert_eq!(restored.state, b"state");
+        assert!(store.load(3).await?.is_none());
+        assert!(!store.path(4).exists());
+
+        store.remove(2).await?;
+        store.remove(2).await?;
+        assert!(store.load(2).await?.is_none());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use crate::server::maybe_stream::MaybeStream;
+use crate::PeerCertificate;
+use std::sync::{Arc, RwLock};
+use tokio::net::TcpStream;
+
+#[cfg(feature = "use_openssl")]
+use openssl::ssl::{Ssl, SslAcceptor};
+#[cfg(feature = "use_openssl")]
+use openssl::x509::X509VerifyResult;
+#[cfg(feature = "use_openssl")]
+use std::pin::Pin;
+#[cfg(feature = "use_openssl")]
+use tokio_openssl::SslStream;
+
+#[cfg(feature = "use_rustls")]
+use tokio_rustls::server::TlsStream;
+#[cfg(feature = "use_rustls")]
+use tokio_rustls::TlsAcceptor;
+
+/// Configuration for TLS (Transport Layer Security) on the server.
+///
+/// Every backend enabled by cargo features is available at the same time,
+/// the backend used by a server is chosen when the server is created.
+#[derive(Clone)]
+pub enum TlsConfig {
+    /// No TLS configuration, connections use plain TCP.
+    None,
+    /// OpenSSL TLS configuration.
+    #[cfg(feature = "use_openssl")]
+    OpenSsl(SslAcceptor),
+    /// Rustls TLS configuration.
+    #[cfg(feature = "use_rustls")]
+    Rustls(TlsAcceptor),
+}
+
+impl TlsConfig {
+    /// Accepts a TCP stream, performing the TLS handshake when configured.
+    ///
+    /// # Arguments
+    ///
+    /// * `tcp_stream` - The accepted TCP stream.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the stream and the verified peer certificate chain, leaf first.
+    #[inline]
+    pub(crate) async fn accept(
+        &self,
+        tcp_stream: TcpStream,
+    ) -> anyhow::Result<(MaybeStream, Vec<PeerCertificate>)> {
+        match self {
+            TlsConfig::None => Ok((MaybeStream::Plain(tcp_stream), Vec::new())),
+            #[cfg(feature = "use_openssl")]
+            TlsConfig::OpenSsl(acceptor) => {
+                let ssl = Ssl::new(acceptor.context())?;
+                let mut stream = SslStream::new(ssl, tcp_stream)?;
+                Pin::new(&mut stream).accept().await?;
+                let certificates = get_ssl_peer_certificates(&stream);
+                Ok((MaybeStream::ServerSsl(stream), certificates))
+            }
+            #[cfg(feature = "use_rustls")]
+            TlsConfig::Rustls(acceptor) => {
+                let stream = acceptor.accept(tcp_stream).await?;
+                let certificates = get_tls_peer_certificates(&stream);
+                Ok((MaybeStream::ServerTls(stream), certificates))
+            }
+        }
+    }
+}
+
+/// A cloneable handle to the TLS configuration of a running server.
+///
+/// Reloading replaces the configuration used for new connections only,
+/// sessions that are already connected keep their current TLS stream.
+#[derive(Clone)]
+pub struct TlsReloadHandle {
+    tls_config: Arc<RwLock<TlsConfig>>,
+}
+
+impl TlsReloadHandle {
+    /// Creates a new `TlsReloadHandle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tls_config` - The initial TLS configuration.
+    ///
+    /// # Returns
+    ///
+    /// A new `TlsReloadHandle` instance.
+    #[inline]
+    pub(crate) fn new(tls_config: TlsConfig) -> TlsReloadHandle {
+        TlsReloadHandle {
+            tls_config: Arc::new(RwLock::new(tls_config)),
+        }
+    }
+
+    /// Replaces the TLS configuration used to accept new connections.
+    ///
+    /// # Arguments
+    ///
+    /// * `tls_config` - The new TLS configuration, e.g. an acceptor built from re-read PEM files.
+    #[inline]
+    pub fn reload(&self, tls_config: TlsConfig) {
+        *self.tls_config.write().unwrap() = tls_config;
+        log::info!("tls config reloaded");
+    }
+
+    /// Gets the TLS configuration currently used to accept new connections.
+    ///
+    /// # Returns
+    ///
+    /// A clone of the current `TlsConfig`.
+    #[inline]
+    pub fn get(&self) -> TlsConfig {
+        self.tls_config.read().unwrap().clone()
+    }
+
+    /// Accepts a TCP stream with the current TLS configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `tcp_stream` - The accepted TCP stream.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the stream and the verified peer certificate chain, leaf first.
+    #[inline]
+    pub(crate) async fn accept(
+        &self,
+        tcp_stream: TcpStream,
+    ) -> anyhow::Result<(MaybeStream, Vec<PeerCertificate>)> {
+        let tls_config = self.get();
+        tls_config.accept(tcp_stream).await
+    }
+}
+
+/// Collects the verified certificate chain presented by the peer.
+///
+/// # Arguments
+///
+/// * `stream` - The accepted OpenSSL stream.
+///
+/// # Returns
+///
+/// The certificate chain, leaf first, or an empty `Vec` if the peer is not verified.
+#[cfg(feature = "use_openssl")]
+#[inline]
+fn get_ssl_peer_certificates(stream: &SslStream<TcpStream>) -> Vec<PeerCertificate> {
+    let ssl = stream.ssl();
+    let mut certificates = Vec::new();
+    if ssl.verify_result() != X509VerifyResult::OK {
+        return certificates;
+    }
+    let leaf = ssl.peer_certificate();
+    let chain = ssl.peer_cert_chain().into_iter().flatten();
+    for cert in leaf.iter().map(|cert| cert.as_ref()).chain(chain) {
+        match PeerCertificate::from_x509(cert) {
+            Ok(cert) => certificates.push(cert),
+            Err(err) => log::error!("read peer certificate error:{}", err),
+        }
+    }
+    certificates
+}
+
+/// Collects the verified certificate chain presented by the peer.
+///
+/// # Arguments
+///
+/// * `stream` - The accepted Rustls stream.
+///
+/// # Returns
+///
+/// The certificate chain, leaf first, or an empty `Vec` if the peer sent none.
+#[cfg(feature = "use_rustls")]
+#[inline]
+fn get_tls_peer_certificates(stream: &TlsStream<TcpStream>) -> Vec<PeerCertificate> {
+    let mut certificates = Vec::new();
+    if let Some(certs) = stream.get_ref().1.peer_certificates() {
+        for der in certs {
+            match PeerCertificate::from_der(der) {
+                Ok(cert) => certificates.push(cert),
+                Err(err) => log::error!("read peer certificate error:{}", err),
+            }
+        }
+    }
+    certificates
+}
@@ -0,0 +1,211 @@
+use crate::server::tls_config::TlsConfig;
+use std::path::{Path, PathBuf};
+
+/// How the server asks connecting clients for a certificate.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ClientAuth {
+    /// Clients are not asked for a certificate.
+    None,
+    /// Clients may present a certificate, which is verified against the CA when present.
+    Optional,
+    /// Clients must present a certificate signed by the CA.
+    Required,
+}
+
+/// Builds a server `TlsConfig` from PEM files.
+///
+/// The builder only keeps the paths, so it can be kept around and built again
+/// to reload rotated certificates through a `TlsReloadHandle`.
+#[derive(Clone, Debug)]
+pub struct TlsConfigBuilder {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    ca_path: Option<PathBuf>,
+    client_auth: ClientAuth,
+    alpn_protocols: Vec<Vec<u8>>,
+}
+
+impl TlsConfigBuilder {
+    /// Creates a new `TlsConfigBuilder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `cert_path` - The PEM file containing the server certificate chain, leaf first.
+    /// * `key_path` - The PEM file containing the server private key.
+    ///
+    /// # Returns
+    ///
+    /// A new `TlsConfigBuilder` instance without client authentication.
+    #[inline]
+    pub fn new(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> TlsConfigBuilder {
+        TlsConfigBuilder {
+            cert_path: cert_path.as_ref().to_path_buf(),
+            key_path: key_path.as_ref().to_path_buf(),
+            ca_path: None,
+            client_auth: ClientAuth::None,
+            alpn_protocols: Vec::new(),
+        }
+    }
+
+    /// Sets the PEM file containing the CA certificates used to verify clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `ca_path` - The CA PEM file.
+    ///
+    /// # Returns
+    ///
+    /// The updated `TlsConfigBuilder`.
+    #[inline]
+    pub fn ca(mut self, ca_path: impl AsRef<Path>) -> TlsConfigBuilder {
+        self.ca_path = Some(ca_path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets how clients are asked for a certificate.
+    ///
+    /// # Arguments
+    ///
+    /// * `client_auth` - The client authentication mode, `Optional` and `Required` need a CA.
+    ///
+    /// # Returns
+    ///
+    /// The updated `TlsConfigBuilder`.
+    #[inline]
+    pub fn client_auth(mut self, client_auth: ClientAuth) -> TlsConfigBuilder {
+        self.client_auth = client_auth;
+        self
+    }
+
+    /// Sets the ALPN protocols offered by the server, in order of preference.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocols` - The protocol names, like `b"netx"`.
+    ///
+    /// # Returns
+    ///
+    /// The updated `TlsConfigBuilder`.
+    #[inline]
+    pub fn alpn_protocols<P: AsRef<[u8]>>(mut self, protocols: &[P]) -> TlsConfigBuilder {
+        self.alpn_protocols = protocols.iter().map(|p| p.as_ref().to_vec()).collect();
+        self
+    }
+
+    /// Gets the CA path, failing if client authentication needs one and it is not set.
+    #[inline]
+    fn client_ca_path(&self) -> anyhow::Result<Option<&Path>> {
+        match (self.client_auth, &self.ca_path) {
+            (ClientAuth::None, _) => Ok(None),
+            (_, Some(ca_path)) => Ok(Some(ca_path)),
+            (_, None) => anyhow::bail!("client auth {:?} requires a ca file", self.client_auth),
+        }
+    }
+
+    /// Builds an OpenSSL `TlsConfig` by reading the PEM files.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `TlsConfig` or an error if a file cannot be loaded.
+    #[cfg(feature = "use_openssl")]
+    pub fn build_openssl(&self) -> anyhow::Result<TlsConfig> {
+        use openssl::ssl::{AlpnError, SslAcceptor, SslFiletype, SslMethod, SslVerifyMode};
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate(SslMethod::tls())?;
+        acceptor.set_private_key_file(&self.key_path, SslFiletype::PEM)?;
+        acceptor.set_certificate_chain_file(&self.cert_path)?;
+        acceptor.check_private_key()?;
+
+        if let Some(ca_path) = self.client_ca_path()? {
+            acceptor.set_ca_file(ca_path)?;
+            let mode = if self.client_auth == ClientAuth::Required {
+                SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT
+            } else {
+                SslVerifyMode::PEER
+            };
+            acceptor.set_verify(mode);
+        }
+
+        if !self.alpn_protocols.is_empty() {
+            let protocols = self.alpn_protocols.clone();
+            acceptor.set_alpn_select_callback(move |_, client| {
+                select_alpn_protocol(&protocols, client).ok_or(AlpnError::NOACK)
+            });
+        }
+
+        Ok(TlsConfig::OpenSsl(acceptor.build()))
+    }
+
+    /// Builds a Rustls `TlsConfig` by reading the PEM files.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `TlsConfig` or an error if a file cannot be loaded.
+    #[cfg(feature = "use_rustls")]
+    pub fn build_rustls(&self) -> anyhow::Result<TlsConfig> {
+        use std::sync::Arc;
+        use tokio_rustls::rustls::pki_types::pem::PemObject;
+        use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+        use tokio_rustls::rustls::server::WebPkiClientVerifier;
+        use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+        use tokio_rustls::TlsAcceptor;
+
+        let cert_chain =
+            CertificateDer::pem_file_iter(&self.cert_path)?.collect::<Result<Vec<_>, _>>()?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)?;
+
+        let builder = ServerConfig::builder();
+        let mut tls_config = match self.client_ca_path()? {
+            Some(ca_path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in CertificateDer::pem_file_iter(ca_path)? {
+                    roots.add(cert?)?;
+                }
+                let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+                let verifier = if self.client_auth == ClientAuth::Optional {
+                    verifier.allow_unauthenticated().build()?
+                } else {
+                    verifier.build()?
+                };
+                builder
+                    .with_client_cert_verifier(verifier)
+                    .with_single_cert(cert_chain, key)?
+            }
+            None => builder
+                .with_no_client_auth()
+                .with_single_cert(cert_chain, key)?,
+        };
+        tls_config.alpn_protocols = self.alpn_protocols.clone();
+
+        Ok(TlsConfig::Rustls(TlsAcceptor::from(Arc::new(tls_config))))
+    }
+}
+
+/// Selects the first server ALPN protocol also offered by the client.
+///
+/// # Arguments
+///
+/// * `protocols` - The server protocols, in order of preference.
+/// * `client` - The client protocols in ALPN wire format.
+///
+/// # Returns
+///
+/// The selected protocol as a slice of `client`, or `None` if there is no overlap.
+#[cfg(feature = "use_openssl")]
+fn select_alpn_protocol<'a>(protocols: &[Vec<u8>], client: &'a [u8]) -> Option<&'a [u8]> {
+    protocols.iter().find_map(|protocol| {
+        let mut offered = client;
+        while let Some((&len, rest)) = offered.split_first() {
+            let len = len as usize;
+            if rest.len() < len {
+                return None;
+            }
+            let (name, rest) = rest.split_at(len);
+            if name == protocol.as_slice() {
+                return Some(name);
+            }
+            offered = rest;
+        }
+        None
+    })
+}